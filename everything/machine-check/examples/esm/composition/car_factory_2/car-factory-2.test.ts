@@ -65,22 +65,143 @@ describe('checkWWFSwarmProtocol for composition with non-wwf subscription', () =
     expect(checkComposedSwarmProtocol(protocols, fail_subs)).toEqual({
       type: 'ERROR',
       errors: [
-        "role R does not subscribe to event types partID, time in branching transitions at state 0 || 0 || 0, but is involved after transition (0 || 0 || 0)--[request@T<partID>]-->(1 || 1 || 0)",
-        "subsequently active role R does not subscribe to events in transition (0 || 2 || 0)--[observe@QCR<obs>]-->(0 || 2 || 1)",
-        "subsequently active role R does not subscribe to events in transition (3 || 2 || 0)--[observe@QCR<obs>]-->(3 || 2 || 1)",
-        "role R does not subscribe to event types obs, part leading to or in joining event in transition (0 || 2 || 1)--[build@R<car>]-->(0 || 3 || 2)",
-        "subsequently active role R does not subscribe to events in transition (2 || 1 || 1)--[deliver@T<part>]-->(0 || 2 || 1)",
-        "role R does not subscribe to event types partID, time in branching transitions at state 0 || 0 || 1, but is involved after transition (0 || 0 || 1)--[request@T<partID>]-->(1 || 1 || 1)"
+        "role R does not subscribe to event types partID, time in branching transitions at state 0 || 0 || 0, but is involved after transition (0 || 0 || 0)--[request@T<partID>]-->(1 || 0 || 1)",
+        "role R does not subscribe to event types partID, time in branching transitions at state 0 || 1 || 0, but is involved after transition (0 || 1 || 0)--[request@T<partID>]-->(1 || 1 || 1)",
+        "subsequently active role R does not subscribe to events in transition (2 || 1 || 1)--[deliver@T<part>]-->(0 || 1 || 2)",
+        "role R does not subscribe to event types obs, part leading to or in joining event in transition (0 || 1 || 2)--[build@R<car>]-->(0 || 2 || 3)",
+        "subsequently active role R does not subscribe to events in transition (0 || 0 || 2)--[observe@QCR<obs>]-->(0 || 1 || 2)",
+        "subsequently active role R does not subscribe to events in transition (3 || 0 || 2)--[observe@QCR<obs>]-->(3 || 1 || 2)"
+      ],
+      details: [
+        {
+          code: 'RoleNotSubscribedToBranch',
+          severity: 'Error',
+          message: 'role R does not subscribe to event types partID, time in branching transitions at state 0 || 0 || 0, but is involved after transition (0 || 0 || 0)--[request@T<partID>]-->(1 || 0 || 1)',
+          component: null,
+          roles: ['R', 'T'],
+          eventTypes: ['partID', 'time'],
+          commands: ['request'],
+          states: ['0 || 0 || 0'],
+          transitions: [
+            { source: '0 || 0 || 0', target: '1 || 0 || 1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+          ],
+          witness: {
+            path: [
+              { source: '0 || 0 || 0', target: '1 || 0 || 1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+            ],
+            logs: [['partID', 'position', 'part', 'obs', 'car'], ['time']],
+            observed: { R: [['car'], []] },
+          },
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'RoleNotSubscribedToBranch',
+          severity: 'Error',
+          message: 'role R does not subscribe to event types partID, time in branching transitions at state 0 || 1 || 0, but is involved after transition (0 || 1 || 0)--[request@T<partID>]-->(1 || 1 || 1)',
+          component: null,
+          roles: ['R', 'T'],
+          eventTypes: ['partID', 'time'],
+          commands: ['request'],
+          states: ['0 || 1 || 0'],
+          transitions: [
+            { source: '0 || 1 || 0', target: '1 || 1 || 1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+          ],
+          witness: {
+            path: [
+              { source: '0 || 0 || 0', target: '0 || 1 || 0', label: { cmd: 'observe', role: 'QCR', logType: ['obs'] } },
+              { source: '0 || 1 || 0', target: '1 || 1 || 1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+            ],
+            logs: [['obs', 'partID', 'position', 'part', 'car'], ['obs', 'time']],
+            observed: { R: [['car'], []] },
+          },
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'LaterActiveRoleNotSubscribed',
+          severity: 'Error',
+          message: 'subsequently active role R does not subscribe to events in transition (2 || 1 || 1)--[deliver@T<part>]-->(0 || 1 || 2)',
+          component: null,
+          roles: ['R', 'T'],
+          eventTypes: ['part'],
+          commands: ['deliver'],
+          states: [],
+          transitions: [
+            { source: '2 || 1 || 1', target: '0 || 1 || 2', label: { cmd: 'deliver', role: 'T', logType: ['part'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'RoleNotSubscribedToJoin',
+          severity: 'Error',
+          message: 'role R does not subscribe to event types obs, part leading to or in joining event in transition (0 || 1 || 2)--[build@R<car>]-->(0 || 2 || 3)',
+          component: null,
+          roles: ['R'],
+          eventTypes: ['obs', 'part', 'car'],
+          commands: ['build'],
+          states: [],
+          transitions: [
+            { source: '0 || 1 || 2', target: '0 || 2 || 3', label: { cmd: 'build', role: 'R', logType: ['car'] } },
+          ],
+          witness: {
+            path: [
+              { source: '0 || 0 || 0', target: '1 || 0 || 1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+              { source: '1 || 0 || 1', target: '2 || 0 || 1', label: { cmd: 'get', role: 'FL', logType: ['position'] } },
+              { source: '2 || 0 || 1', target: '0 || 0 || 2', label: { cmd: 'deliver', role: 'T', logType: ['part'] } },
+              { source: '0 || 0 || 2', target: '0 || 1 || 2', label: { cmd: 'observe', role: 'QCR', logType: ['obs'] } },
+              { source: '0 || 1 || 2', target: '0 || 2 || 3', label: { cmd: 'build', role: 'R', logType: ['car'] } },
+            ],
+            logs: [['partID', 'position', 'part', 'obs', 'car'], ['partID', 'position', 'obs', 'part', 'car']],
+            observed: { R: [['car'], ['car']] },
+          },
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'LaterActiveRoleNotSubscribed',
+          severity: 'Error',
+          message: 'subsequently active role R does not subscribe to events in transition (0 || 0 || 2)--[observe@QCR<obs>]-->(0 || 1 || 2)',
+          component: null,
+          roles: ['R', 'QCR'],
+          eventTypes: ['obs'],
+          commands: ['observe'],
+          states: [],
+          transitions: [
+            { source: '0 || 0 || 2', target: '0 || 1 || 2', label: { cmd: 'observe', role: 'QCR', logType: ['obs'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'LaterActiveRoleNotSubscribed',
+          severity: 'Error',
+          message: 'subsequently active role R does not subscribe to events in transition (3 || 0 || 2)--[observe@QCR<obs>]-->(3 || 1 || 2)',
+          component: null,
+          roles: ['R', 'QCR'],
+          eventTypes: ['obs'],
+          commands: ['observe'],
+          states: [],
+          transitions: [
+            { source: '3 || 0 || 2', target: '3 || 1 || 2', label: { cmd: 'observe', role: 'QCR', logType: ['obs'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
       ]
       /* errors: [
         "active role does not subscribe to any of its emitted event types in transition (0 || 0 || 0)--[close@D<time>]-->(3 || 0 || 0)",
-        "subsequently active role D does not subscribe to events in transition (2 || 1 || 0)--[deliver@T<part>]-->(0 || 2 || 0)",
-        "active role does not subscribe to any of its emitted event types in transition (0 || 2 || 0)--[close@D<time>]-->(3 || 2 || 0)",
-        "active role does not subscribe to any of its emitted event types in transition (0 || 2 || 1)--[close@D<time>]-->(3 || 2 || 1)",
-        "active role does not subscribe to any of its emitted event types in transition (0 || 3 || 2)--[close@D<time>]-->(3 || 3 || 2)",
+        "subsequently active role D does not subscribe to events in transition (2 || 0 || 1)--[deliver@T<part>]-->(0 || 0 || 2)",
+        "active role does not subscribe to any of its emitted event types in transition (0 || 0 || 2)--[close@D<time>]-->(3 || 0 || 2)",
+        "active role does not subscribe to any of its emitted event types in transition (0 || 1 || 2)--[close@D<time>]-->(3 || 1 || 2)",
+        "active role does not subscribe to any of its emitted event types in transition (0 || 2 || 3)--[close@D<time>]-->(3 || 2 || 3)",
         "active role does not subscribe to any of its emitted event types in transition (0 || 3 || 3)--[close@D<time>]-->(3 || 3 || 3)",
-        "subsequently active role D does not subscribe to events in transition (2 || 1 || 1)--[deliver@T<part>]-->(0 || 2 || 1)",
-        "active role does not subscribe to any of its emitted event types in transition (0 || 0 || 1)--[close@D<time>]-->(3 || 0 || 1)"
+        "subsequently active role D does not subscribe to events in transition (2 || 1 || 1)--[deliver@T<part>]-->(0 || 1 || 2)",
+        "active role does not subscribe to any of its emitted event types in transition (0 || 1 || 0)--[close@D<time>]-->(3 || 1 || 0)"
       ] */
     })
   })
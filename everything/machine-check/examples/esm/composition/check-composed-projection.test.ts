@@ -133,7 +133,23 @@ describe('checkComposedProjection', () => {
         ),
       ).toEqual({
         type: 'ERROR',
-        errors: ["missing transition deliver/part in state S21 (from reference state 2 || 1)"]
+        errors: ["missing transition deliver/part in state S21 (from reference state 2 || 1) after [partID?, position?]"],
+        details: [
+          {
+            code: 'MissingTransition',
+            severity: 'Error',
+            message: 'missing transition deliver/part in state S21 (from reference state 2 || 1) after [partID?, position?]',
+            component: null,
+            roles: [],
+            eventTypes: ['part'],
+            commands: ['deliver'],
+            states: ['S21', '2 || 1'],
+            transitions: [],
+            witness: null,
+            span: null,
+            trace: [{ tag: 'Input', eventType: 'partID' }, { tag: 'Input', eventType: 'position' }],
+          },
+        ]
       })
     })
   })
@@ -82,6 +82,25 @@ describe('check confusion-ful protocols G1 and G3', () => {
       type: 'ERROR',
       errors: [
         "event type report emitted in more than one transition: (0)--[build@F<report>]-->(1), (1)--[test@TR<report>]-->(2)",
+      ],
+      details: [
+        {
+          code: 'EventEmittedMultipleTimes',
+          severity: 'Error',
+          message: 'event type report emitted in more than one transition: (0)--[build@F<report>]-->(1), (1)--[test@TR<report>]-->(2)',
+          component: 0,
+          roles: ['F', 'TR'],
+          eventTypes: ['report'],
+          commands: ['build', 'test'],
+          states: [],
+          transitions: [
+            { source: '0', target: '1', label: { cmd: 'build', role: 'F', logType: ['report'] } },
+            { source: '1', target: '2', label: { cmd: 'test', role: 'TR', logType: ['report'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
       ]
     })
   })
@@ -96,6 +115,78 @@ describe('checkWWFSwarmProtocol G1', () => {
         "active role does not subscribe to any of its emitted event types in transition (2)--[deliver@T<part>]-->(0)",
         "subsequently active role D does not subscribe to events in transition (2)--[deliver@T<part>]-->(0)",
         "subsequently active role T does not subscribe to events in transition (2)--[deliver@T<part>]-->(0)",
+      ],
+      details: [
+        {
+          code: 'RoleNotSubscribedToBranch',
+          severity: 'Error',
+          message: 'role FL does not subscribe to event types time in branching transitions at state 0, but is involved after transition (0)--[request@T<partID>]-->(1)',
+          component: 0,
+          roles: ['FL', 'T'],
+          eventTypes: ['time', 'partID'],
+          commands: ['request'],
+          states: ['0'],
+          transitions: [
+            { source: '0', target: '1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+          ],
+          witness: {
+            path: [
+              { source: '0', target: '1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+            ],
+            logs: [['partID', 'position'], ['time']],
+            observed: { FL: [['partID', 'position'], []] },
+          },
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'ActiveRoleNotSubscribed',
+          severity: 'Error',
+          message: 'active role does not subscribe to any of its emitted event types in transition (2)--[deliver@T<part>]-->(0)',
+          component: 0,
+          roles: ['T'],
+          eventTypes: ['part'],
+          commands: ['deliver'],
+          states: [],
+          transitions: [
+            { source: '2', target: '0', label: { cmd: 'deliver', role: 'T', logType: ['part'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'LaterActiveRoleNotSubscribed',
+          severity: 'Error',
+          message: 'subsequently active role D does not subscribe to events in transition (2)--[deliver@T<part>]-->(0)',
+          component: 0,
+          roles: ['D', 'T'],
+          eventTypes: ['part'],
+          commands: ['deliver'],
+          states: [],
+          transitions: [
+            { source: '2', target: '0', label: { cmd: 'deliver', role: 'T', logType: ['part'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'LaterActiveRoleNotSubscribed',
+          severity: 'Error',
+          message: 'subsequently active role T does not subscribe to events in transition (2)--[deliver@T<part>]-->(0)',
+          component: 0,
+          roles: ['T'],
+          eventTypes: ['part'],
+          commands: ['deliver'],
+          states: [],
+          transitions: [
+            { source: '2', target: '0', label: { cmd: 'deliver', role: 'T', logType: ['part'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
       ]
     })
   })
@@ -112,6 +112,40 @@ describe('checkSwarmProtocol for protocols with exact wwf subscription', () => {
         "subsequently involved role D does not subscribe to guard in transition (1)--[get@FL<position>]-->(2)",
         "subsequently involved role FL does not subscribe to guard in transition (2)--[deliver@T<part>]-->(0)"
       ],
+      details: [
+        {
+          code: 'LaterInvolvedNotGuarded',
+          severity: 'Error',
+          message: 'subsequently involved role D does not subscribe to guard in transition (1)--[get@FL<position>]-->(2)',
+          component: null,
+          roles: ['D', 'FL'],
+          eventTypes: ['position'],
+          commands: ['get'],
+          states: [],
+          transitions: [
+            { source: '1', target: '2', label: { cmd: 'get', role: 'FL', logType: ['position'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'LaterInvolvedNotGuarded',
+          severity: 'Error',
+          message: 'subsequently involved role FL does not subscribe to guard in transition (2)--[deliver@T<part>]-->(0)',
+          component: null,
+          roles: ['FL', 'T'],
+          eventTypes: ['part'],
+          commands: ['deliver'],
+          states: [],
+          transitions: [
+            { source: '2', target: '0', label: { cmd: 'deliver', role: 'T', logType: ['part'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+      ]
     })
   })
 
@@ -121,6 +155,24 @@ describe('checkSwarmProtocol for protocols with exact wwf subscription', () => {
       errors: [
         "subsequently involved role F does not subscribe to guard in transition (0)--[request@T<partID>]-->(1)"
       ],
+      details: [
+        {
+          code: 'LaterInvolvedNotGuarded',
+          severity: 'Error',
+          message: 'subsequently involved role F does not subscribe to guard in transition (0)--[request@T<partID>]-->(1)',
+          component: null,
+          roles: ['F', 'T'],
+          eventTypes: ['partID'],
+          commands: ['request'],
+          states: [],
+          transitions: [
+            { source: '0', target: '1', label: { cmd: 'request', role: 'T', logType: ['partID'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+      ]
     })
   })
 
@@ -130,6 +182,24 @@ describe('checkSwarmProtocol for protocols with exact wwf subscription', () => {
       errors: [
         "subsequently involved role QCR does not subscribe to guard in transition (0)--[build@F<car>]-->(1)"
       ],
+      details: [
+        {
+          code: 'LaterInvolvedNotGuarded',
+          severity: 'Error',
+          message: 'subsequently involved role QCR does not subscribe to guard in transition (0)--[build@F<car>]-->(1)',
+          component: null,
+          roles: ['QCR', 'F'],
+          eventTypes: ['car'],
+          commands: ['build'],
+          states: [],
+          transitions: [
+            { source: '0', target: '1', label: { cmd: 'build', role: 'F', logType: ['car'] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+      ]
     })
   })
 })
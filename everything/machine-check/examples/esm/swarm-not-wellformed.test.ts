@@ -55,6 +55,42 @@ describe('checkSwarmProtocol', () => {
         `guard event type ${Events.Opening.type} appears in transitions from multiple states`,
         `guard event type ${Events.Closing.type} appears in transitions from multiple states`
       ],
+      details: [
+        {
+          code: 'GuardNotInvariant',
+          severity: 'Error',
+          message: `guard event type ${Events.Opening.type} appears in transitions from multiple states`,
+          component: null,
+          roles: ['Control', 'Door'],
+          eventTypes: [Events.Opening.type],
+          commands: ['open', 'update'],
+          states: [],
+          transitions: [
+            { source: 'Closed', target: 'Opening', label: { cmd: 'open', role: 'Control', logType: [Events.Opening.type] } },
+            { source: 'Opening', target: 'Opening', label: { cmd: 'update', role: 'Door', logType: [Events.Opening.type] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+        {
+          code: 'GuardNotInvariant',
+          severity: 'Error',
+          message: `guard event type ${Events.Closing.type} appears in transitions from multiple states`,
+          component: null,
+          roles: ['Control', 'Door'],
+          eventTypes: [Events.Closing.type],
+          commands: ['close', 'update'],
+          states: [],
+          transitions: [
+            { source: 'Open', target: 'Closing', label: { cmd: 'close', role: 'Control', logType: [Events.Closing.type] } },
+            { source: 'Closing', target: 'Closing', label: { cmd: 'update', role: 'Door', logType: [Events.Closing.type] } },
+          ],
+          witness: null,
+          span: null,
+          trace: [],
+        },
+      ]
     })
   })
 })
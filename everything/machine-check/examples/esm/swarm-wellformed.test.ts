@@ -229,6 +229,36 @@ describe('checkProjection', () => {
           `missing transition ${Events.Opening.type}? in state Closed (from reference state Closed)`,
           `extraneous transition ${Events.OpeningStatus.type}? in state Closed`,
         ],
+        details: [
+          {
+            code: 'MissingTransition',
+            severity: 'Error',
+            message: `missing transition ${Events.Opening.type}? in state Closed (from reference state Closed)`,
+            component: null,
+            roles: [],
+            eventTypes: [Events.Opening.type],
+            commands: [],
+            states: ['Closed'],
+            transitions: [],
+            witness: null,
+            span: null,
+            trace: [],
+          },
+          {
+            code: 'ExtraneousTransition',
+            severity: 'Error',
+            message: `extraneous transition ${Events.OpeningStatus.type}? in state Closed`,
+            component: null,
+            roles: [],
+            eventTypes: [Events.OpeningStatus.type],
+            commands: [],
+            states: ['Closed'],
+            transitions: [],
+            witness: null,
+            span: null,
+            trace: [],
+          },
+        ]
       })
     })
 
@@ -246,6 +276,36 @@ describe('checkProjection', () => {
           `missing transition ${Events.Opening.type}? in state Closed (from reference state Closed)`,
           `extraneous transition ${Events.OpeningStatus.type}? in state Closed`,
         ],
+        details: [
+          {
+            code: 'MissingTransition',
+            severity: 'Error',
+            message: `missing transition ${Events.Opening.type}? in state Closed (from reference state Closed)`,
+            component: null,
+            roles: [],
+            eventTypes: [Events.Opening.type],
+            commands: [],
+            states: ['Closed'],
+            transitions: [],
+            witness: null,
+            span: null,
+            trace: [],
+          },
+          {
+            code: 'ExtraneousTransition',
+            severity: 'Error',
+            message: `extraneous transition ${Events.OpeningStatus.type}? in state Closed`,
+            component: null,
+            roles: [],
+            eventTypes: [Events.OpeningStatus.type],
+            commands: [],
+            states: ['Closed'],
+            transitions: [],
+            witness: null,
+            span: null,
+            trace: [],
+          },
+        ]
       })
     })
   })
@@ -1,16 +1,31 @@
-use composition_swarm::{proto_info_to_error_report, swarms_to_proto_info, ErrorReport};
+use composition_swarm::{proto_info_to_error_report, swarms_to_proto_info};
 use composition_types::{
-    Granularity, ProjectionInfo,
+    ConformanceReport, ExplainedSubscription, Granularity, LanguageComparison, LogEntry, MinimalityReport,
+    ModelCheckConfig, ModelCheckReport, ProjectionInfo, SimulationReport, SimulationStep,
+    SubscriptionComparison, SubscriptionRepair, SwarmSimulationConfig, SwarmSimulationReport,
 };
 
 use crate::composition::composition_types::InterfacingProtocols;
+use crate::types::{ErrorCode, ErrorInfo};
 
 use super::*;
 
+#[cfg(test)]
+mod composition_fixtures;
 mod composition_machine;
+mod composition_model_check;
+mod composition_monitor;
+mod composition_session;
+mod composition_simulation;
+mod composition_subscription;
 mod composition_swarm;
+mod composition_swarm_simulation;
 pub mod composition_types;
 
+pub use composition_session::CompositionSession;
+pub use composition_monitor::parse_log;
+pub use composition_machine::{determinize, language_equivalent, minimize};
+
 macro_rules! deserialize_subs {
     ($subs:expr, $err_exp:expr) => {
         match serde_json::from_str::<Subscriptions>(&$subs) {
@@ -22,16 +37,35 @@ macro_rules! deserialize_subs {
 
 #[wasm_bindgen]
 pub fn check_composed_swarm(protos: InterfacingProtocols, subs: String) -> CheckResult {
-    let subs = deserialize_subs!(subs, |e| CheckResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    let subs = deserialize_subs!(subs, |e| CheckResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
     let error_report = composition::composition_swarm::check(protos, &subs);
     if error_report.is_empty() {
         CheckResult::OK
     } else {
-        CheckResult::ERROR {
-            errors: error_report_to_strings(error_report),
-        }
+        CheckResult::from_errors(error_report.to_infos())
+    }
+}
+
+// Check well-formedness exploring the composition on the fly with partial-order reduction.
+// Reports violations once per event type and role, or only the first one if stop_at_first is set.
+// stop_at_first only stops exploration early at causal consistency violations, see composition_swarm::check_on_the_fly().
+#[wasm_bindgen]
+pub fn check_composed_swarm_on_the_fly(
+    protos: InterfacingProtocols,
+    subs: String,
+    stop_at_first: bool,
+) -> CheckResult {
+    let subs = deserialize_subs!(subs, |e| CheckResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    let error_report =
+        composition::composition_swarm::check_on_the_fly(protos, &subs, true, stop_at_first);
+    if error_report.is_empty() {
+        CheckResult::OK
+    } else {
+        CheckResult::from_errors(error_report.to_infos())
     }
 }
 
@@ -40,17 +74,15 @@ pub fn exact_well_formed_sub(
     protos: InterfacingProtocols,
     subs: String,
 ) -> DataResult<Subscriptions> {
-    let subs = deserialize_subs!(subs, |e| DataResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
     let result = composition_swarm::exact_well_formed_sub(protos, &subs);
     match result {
         Ok(subscriptions) => DataResult::OK {
             data: subscriptions,
         }, //dok(subscriptions),
-        Err(error_report) => DataResult::ERROR {
-            errors: error_report_to_strings(error_report),
-        }, //derr::<Subscriptions>(error_report_to_strings(error_report)),
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
     }
 }
 
@@ -60,17 +92,190 @@ pub fn overapproximated_well_formed_sub(
     subs: String,
     granularity: Granularity,
 ) -> DataResult<Subscriptions> {
-    let subs = deserialize_subs!(subs, |e| DataResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
     let result = composition_swarm::overapprox_well_formed_sub(protos, &subs, granularity);
     match result {
         Ok(subscriptions) => DataResult::OK {
             data: subscriptions,
         },
-        Err(error_report) => DataResult::ERROR {
-            errors: error_report_to_strings(error_report),
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// exact_well_formed_sub() together with the rule and transition justifying each entry of the subscription.
+#[wasm_bindgen]
+pub fn exact_well_formed_sub_explained(
+    protos: InterfacingProtocols,
+    subs: String,
+) -> DataResult<ExplainedSubscription> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    let result = composition_swarm::exact_well_formed_sub_justified(protos, &subs);
+    match result {
+        Ok((subscription, justifications)) => DataResult::OK {
+            data: ExplainedSubscription {
+                subscription,
+                justifications,
+            },
+        },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// overapproximated_well_formed_sub() together with the rule and event types justifying each entry of the subscription.
+#[wasm_bindgen]
+pub fn overapproximated_well_formed_sub_explained(
+    protos: InterfacingProtocols,
+    subs: String,
+    granularity: Granularity,
+) -> DataResult<ExplainedSubscription> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    let result =
+        composition_swarm::overapprox_well_formed_sub_justified(protos, &subs, granularity);
+    match result {
+        Ok((subscription, justifications)) => DataResult::OK {
+            data: ExplainedSubscription {
+                subscription,
+                justifications,
+            },
         },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// The exact subscription and every granularity of overapproximation, with the event types each overapproximation adds.
+#[wasm_bindgen]
+pub fn compare_well_formed_subs(
+    protos: InterfacingProtocols,
+    subs: String,
+) -> DataResult<SubscriptionComparison> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    match composition_swarm::compare_well_formed_subs(protos, &subs) {
+        Ok(comparison) => DataResult::OK { data: comparison },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// The entries of a well-formed subscription that can be removed together, chosen greedily, and the errors removing each of the other entries would cause.
+#[wasm_bindgen]
+pub fn check_subscription_minimality(
+    protos: InterfacingProtocols,
+    subs: String,
+) -> DataResult<MinimalityReport> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    match composition_swarm::check_minimality(protos, &subs) {
+        Ok(report) => DataResult::OK { data: report },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// Extend a subscription failing the well-formedness check to the smallest well-formed subscription containing it
+// and report the added entries together with the errors each of them fixes.
+#[wasm_bindgen]
+pub fn repair_subscription(
+    protos: InterfacingProtocols,
+    subs: String,
+) -> DataResult<SubscriptionRepair> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    match composition_swarm::repair_subscription(protos, &subs) {
+        Ok(repair) => DataResult::OK { data: repair },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// Step the expanded composition and the projection of every role in lockstep, see composition_simulation::simulate().
+// The steps are given as a JSON array of commands and event types.
+#[wasm_bindgen]
+pub fn simulate(
+    protos: InterfacingProtocols,
+    subs: String,
+    steps: String,
+    minimize: bool,
+) -> DataResult<SimulationReport> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    let steps = match serde_json::from_str::<Vec<SimulationStep>>(&steps) {
+        Ok(steps) => steps,
+        Err(e) => {
+            return DataResult::from_errors(vec![ErrorInfo::new(
+                ErrorCode::ParseError,
+                format!("parsing simulation steps: {}", e),
+            )])
+        }
+    };
+    match composition_simulation::simulate(protos, &subs, &steps, minimize) {
+        Ok(report) => DataResult::OK { data: report },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// Check a recorded event log given as JSON lines against the expanded composition, see composition_monitor::check_log().
+#[wasm_bindgen]
+pub fn check_event_log(protos: InterfacingProtocols, log: String) -> DataResult<ConformanceReport> {
+    let log = match composition_monitor::parse_log(&log) {
+        Ok(log) => log,
+        Err(e) => {
+            return DataResult::from_errors(vec![ErrorInfo::new(
+                ErrorCode::ParseError,
+                format!("parsing event log: {}", e),
+            )])
+        }
+    };
+    check_parsed_event_log(protos, &log)
+}
+
+// Like check_event_log() but for a log already parsed with parse_log().
+pub fn check_parsed_event_log(
+    protos: InterfacingProtocols,
+    log: &[LogEntry],
+) -> DataResult<ConformanceReport> {
+    match composition_monitor::check_log(protos, log) {
+        Ok(report) => DataResult::OK { data: report },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// Simulate the composition as a swarm with local logs, delays and partitions, see composition_swarm_simulation::simulate_swarm().
+#[wasm_bindgen]
+pub fn simulate_swarm(
+    protos: InterfacingProtocols,
+    subs: String,
+    config: SwarmSimulationConfig,
+) -> DataResult<SwarmSimulationReport> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    match composition_swarm_simulation::simulate_swarm(protos, &subs, config) {
+        Ok(report) => DataResult::OK { data: report },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+    }
+}
+
+// Explore every schedule of emissions and deliveries up to a bound, see composition_model_check::model_check().
+#[wasm_bindgen]
+pub fn model_check(
+    protos: InterfacingProtocols,
+    subs: String,
+    config: ModelCheckConfig,
+) -> DataResult<ModelCheckReport> {
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    match composition_model_check::model_check(protos, &subs, config) {
+        Ok(report) => DataResult::OK { data: report },
+        Err(error_report) => DataResult::from_errors(error_report.to_infos()),
     }
 }
 
@@ -81,15 +286,17 @@ pub fn revised_projection(
     role: Role,
     minimize: bool,
 ) -> DataResult<MachineType> {
-    let subs = deserialize_subs!(subs, |e| DataResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
+    let desugared_commands = composition_swarm::desugared_commands(&proto);
     let (swarm, initial, errors) = composition_swarm::from_json(proto);
     let Some(initial) = initial else {
-        return DataResult::ERROR { errors };
+        return DataResult::from_errors(errors);
     };
     let (proj, initial) =
         composition::composition_machine::project(&swarm, initial, &subs, role, minimize);
+    let proj = composition_machine::fold_desugared_commands(proj, &desugared_commands);
     DataResult::OK {
         data: composition::composition_machine::to_json_machine(proj, initial),
     }
@@ -102,14 +309,14 @@ pub fn project_combine(
     role: Role,
     minimize: bool,
 ) -> DataResult<MachineType> {
-    let subs = deserialize_subs!(subs, |e| DataResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
     let proto_info = swarms_to_proto_info(protos);
     if !proto_info.no_errors() {
-        return DataResult::ERROR {
-            errors: error_report_to_strings(proto_info_to_error_report(proto_info)),
-        }; //derr::<Machine>(error_report_to_strings(proto_info_to_error_report(proto_info)));
+        return DataResult::from_errors(
+            proto_info_to_error_report(proto_info).to_infos(),
+        );
     }
 
     let (proj, proj_initial) =
@@ -128,25 +335,25 @@ pub fn projection_information(
     machine: MachineType,
     minimize: bool,
 ) -> DataResult<ProjectionInfo> {
-    let subs = deserialize_subs!(subs, |e| DataResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    let subs = deserialize_subs!(subs, |e| DataResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
     let proto_info = swarms_to_proto_info(protos);
     if !proto_info.no_errors() {
-        return DataResult::ERROR {
-            errors: error_report_to_strings(proto_info_to_error_report(proto_info)),
-        };
+        return DataResult::from_errors(
+            proto_info_to_error_report(proto_info).to_infos(),
+        );
     }
     let (machine, initial, m_errors) = machine::from_json(machine);
     let machine_problem = !m_errors.is_empty();
     let mut errors = vec![];
     errors.extend(m_errors);
     let Some(initial) = initial else {
-        errors.push(format!("initial machine state has no transitions"));
-        return DataResult::ERROR { errors };
+        errors.push(ErrorInfo::no_initial_machine_state());
+        return DataResult::from_errors(errors);
     };
     if machine_problem {
-        return DataResult::ERROR { errors };
+        return DataResult::from_errors(errors);
     }
     match composition::composition_machine::projection_information(
         &proto_info,
@@ -159,9 +366,10 @@ pub fn projection_information(
         Some(projection_info) => DataResult::OK {
             data: projection_info,
         },
-        None => DataResult::ERROR {
-            errors: vec![format!("invalid index {}", k)],
-        },
+        None => DataResult::from_errors(vec![ErrorInfo::new(
+            ErrorCode::InvalidIndex,
+            format!("invalid index {}", k),
+        )]),
     }
 }
 
@@ -172,58 +380,137 @@ pub fn check_composed_projection(
     role: Role,
     machine: MachineType,
 ) -> CheckResult {
-    let subs = deserialize_subs!(subs, |e| CheckResult::ERROR {
-        errors: vec![format!("parsing subscriptions: {}", e)]
-    });
+    check_composed_machine(protos, subs, role, machine, |proj, proj_initial, machine, initial| {
+        composition_machine::equivalent(proj, proj_initial, machine, initial)
+            .iter()
+            .map(|e| e.to_info(proj, proj_initial, machine, initial))
+            .collect()
+    })
+}
+
+// Like check_composed_projection() but machine may leave out commands of the projection, see machine::refines().
+#[wasm_bindgen]
+pub fn check_composed_refinement(
+    protos: InterfacingProtocols,
+    subs: String,
+    role: Role,
+    machine: MachineType,
+) -> CheckResult {
+    check_composed_machine(protos, subs, role, machine, |proj, proj_initial, machine, initial| {
+        machine::refines(proj, proj_initial, machine, initial)
+            .iter()
+            .map(|v| v.to_info())
+            .collect()
+    })
+}
+
+type OptionGraph = petgraph::Graph<Option<State>, MachineLabel>;
+
+// Project the composition onto role and compare machine with the projection, see machine::check_against_projection().
+fn check_composed_machine(
+    protos: InterfacingProtocols,
+    subs: String,
+    role: Role,
+    machine: MachineType,
+    compare: impl FnOnce(&OptionGraph, NodeId, &OptionGraph, NodeId) -> Vec<ErrorInfo>,
+) -> CheckResult {
+    let subs = deserialize_subs!(subs, |e| CheckResult::from_errors(vec![
+        ErrorInfo::invalid_subscriptions(e)
+    ]));
     let proto_info = swarms_to_proto_info(protos);
     if !proto_info.no_errors() {
-        return CheckResult::ERROR {
-            errors: error_report_to_strings(proto_info_to_error_report(proto_info)),
-        };
+        return CheckResult::from_errors(
+            proto_info_to_error_report(proto_info).to_infos(),
+        );
     }
 
-    let (proj, proj_initial) =
-        composition_machine::project_combine(&proto_info, &subs, role, false);
-    let (machine, json_initial, m_errors) = machine::from_json(machine);
-    let machine_problem = !m_errors.is_empty();
-    let mut errors = vec![];
-    errors.extend(m_errors);
-    let Some(json_initial) = json_initial else {
-        errors.push(format!("initial machine state has no transitions"));
-        return CheckResult::ERROR { errors };
+    let (proj, Some(proj_initial)) =
+        composition_machine::project_combine(&proto_info, &subs, role, false)
+    else {
+        unreachable!("project_combine() always returns an initial state")
     };
-    if machine_problem {
-        return CheckResult::ERROR { errors };
+    CheckResult::from_errors(machine::check_against_projection(
+        &proj,
+        proj_initial,
+        machine,
+        compare,
+    ))
+}
+
+// Determinize a machine independently of any protocol, see composition_machine::determinize().
+#[wasm_bindgen]
+pub fn determinize_machine(machine: MachineType) -> DataResult<MachineType> {
+    let (graph, initial) = match machine::checked_from_json(machine) {
+        Ok(machine) => machine,
+        Err(errors) => return DataResult::from_errors(errors),
+    };
+    let (dfa, dfa_initial) = composition_machine::determinize(&graph, initial);
+    DataResult::OK {
+        data: composition_machine::from_option_to_machine(dfa, dfa_initial),
     }
+}
 
-    errors.extend(
-        composition_machine::equivalent(&proj, proj_initial.unwrap(), &machine, json_initial)
-            .into_iter()
-            .map(machine::Error::convert(&proj, &machine)),
-    );
+// Determinize and minimize a machine independently of any protocol, see composition_machine::minimize().
+#[wasm_bindgen]
+pub fn minimize_machine(machine: MachineType) -> DataResult<MachineType> {
+    let (graph, initial) = match machine::checked_from_json(machine) {
+        Ok(machine) => machine,
+        Err(errors) => return DataResult::from_errors(errors),
+    };
+    let (minimal, minimal_initial) = composition_machine::minimize(&graph, initial);
+    DataResult::OK {
+        data: composition_machine::from_option_to_machine(minimal, minimal_initial),
+    }
+}
 
-    if errors.is_empty() {
-        CheckResult::OK
-    } else {
-        CheckResult::ERROR { errors }
+// Decide whether two machines accept the same sequences of transitions, see composition_machine::language_equivalent().
+#[wasm_bindgen]
+pub fn compare_machine_languages(
+    left: MachineType,
+    right: MachineType,
+) -> DataResult<LanguageComparison> {
+    let (left, li) = match machine::checked_from_json(left) {
+        Ok(machine) => machine,
+        Err(errors) => return DataResult::from_errors(errors),
+    };
+    let (right, ri) = match machine::checked_from_json(right) {
+        Ok(machine) => machine,
+        Err(errors) => return DataResult::from_errors(errors),
+    };
+    let difference = composition_machine::language_equivalent(&left, li, &right, ri);
+    DataResult::OK {
+        data: LanguageComparison {
+            equivalent: difference.is_none(),
+            difference,
+        },
     }
 }
 
+// The order in which the protocols are composed, see composition_swarm::composition_order().
+#[wasm_bindgen]
+pub fn composition_order(protos: InterfacingProtocols) -> Vec<usize> {
+    composition_swarm::composition_order(&protos)
+}
+
 #[wasm_bindgen]
 pub fn compose_protocols(protos: InterfacingProtocols) -> DataResult<SwarmProtocolType> {
+    let desugared_commands: BTreeMap<_, _> = protos
+        .0
+        .iter()
+        .flat_map(composition_swarm::desugared_commands)
+        .collect();
     let composition = composition_swarm::compose_protocols(protos);
 
     match composition {
         Ok((graph, initial)) => DataResult::OK {
-            data: composition_swarm::to_swarm_json(graph, initial),
-        },
-        Err(errors) => DataResult::ERROR {
-            errors: error_report_to_strings(errors),
+            data: composition_swarm::to_swarm_json(graph, initial, &desugared_commands),
         },
+        Err(errors) => DataResult::from_errors(errors.to_infos()),
     }
 }
 
-fn error_report_to_strings(error_report: ErrorReport) -> Vec<String> {
+#[cfg(test)]
+fn error_report_to_strings(error_report: composition_swarm::ErrorReport) -> Vec<String> {
     error_report
         .errors()
         .into_iter()
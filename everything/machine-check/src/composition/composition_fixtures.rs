@@ -0,0 +1,92 @@
+// Protocols shared by the tests of several modules.
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::composition_types::InterfacingProtocols;
+use crate::types::{EventType, Role};
+use crate::{Subscriptions, SwarmProtocolType};
+
+// Example from coplaws slides
+pub fn get_proto1() -> SwarmProtocolType {
+    serde_json::from_str::<SwarmProtocolType>(
+        r#"{
+            "initial": "0",
+            "transitions": [
+                { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                { "source": "1", "target": "2", "label": { "cmd": "get", "logType": ["pos"], "role": "FL" } },
+                { "source": "2", "target": "0", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+                { "source": "0", "target": "3", "label": { "cmd": "close", "logType": ["time"], "role": "D" } }
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+pub fn get_proto2() -> SwarmProtocolType {
+    serde_json::from_str::<SwarmProtocolType>(
+        r#"{
+            "initial": "0",
+            "transitions": [
+                { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                { "source": "1", "target": "2", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+                { "source": "2", "target": "3", "label": { "cmd": "build", "logType": ["car"], "role": "F" } }
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+// Like get_proto2(), but build emits two event types.
+pub fn get_proto2_done() -> SwarmProtocolType {
+    serde_json::from_str::<SwarmProtocolType>(
+        r#"{
+            "initial": "0",
+            "transitions": [
+                { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                { "source": "1", "target": "2", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+                { "source": "2", "target": "3", "label": { "cmd": "build", "logType": ["car", "done"], "role": "F" } }
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+pub fn get_interfacing_swarms_1() -> InterfacingProtocols {
+    InterfacingProtocols(vec![get_proto1(), get_proto2()])
+}
+
+pub fn get_interfacing_swarms_1_done() -> InterfacingProtocols {
+    InterfacingProtocols(vec![get_proto1(), get_proto2_done()])
+}
+
+// R1 and R2 may both think they decide the branch at 0.
+pub fn get_branching_proto() -> SwarmProtocolType {
+    serde_json::from_str::<SwarmProtocolType>(
+        r#"{
+            "initial": "0",
+            "transitions": [
+                { "source": "0", "target": "1", "label": { "cmd": "left", "logType": ["a"], "role": "R1" } },
+                { "source": "0", "target": "2", "label": { "cmd": "right", "logType": ["b"], "role": "R2" } },
+                { "source": "1", "target": "3", "label": { "cmd": "c", "logType": ["c"], "role": "R3" } },
+                { "source": "2", "target": "4", "label": { "cmd": "d", "logType": ["d"], "role": "R3" } }
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+// Subscription for get_branching_proto() where R1 and R2 do not learn about the branch taken by the other one.
+pub fn get_uninformed_branch_subs() -> Subscriptions {
+    BTreeMap::from([
+        (Role::new("R1"), BTreeSet::from([EventType::new("a")])),
+        (Role::new("R2"), BTreeSet::from([EventType::new("b")])),
+        (
+            Role::new("R3"),
+            BTreeSet::from([
+                EventType::new("a"),
+                EventType::new("b"),
+                EventType::new("c"),
+                EventType::new("d"),
+            ]),
+        ),
+    ])
+}
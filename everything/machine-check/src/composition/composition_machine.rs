@@ -1,13 +1,14 @@
 use super::{
     composition_types::{
         get_branching_joining_proto_info, unord_event_pair, BranchMap, EventLabel,
-        ProjToMachineStates, ProjectionInfo, ProtoInfo, ProtoStruct, UnordEventPair,
+        LanguageDifference, ProjToMachineStates, ProjectionInfo, ProtoInfo, ProtoStruct,
+        UnordEventPair,
     },
     types::{Command, StateName, Transition},
     EventType, MachineLabel, MachineType, NodeId, Role, State, Subscriptions, SwarmLabel,
 };
 use crate::{
-    composition::composition_swarm::transitive_closure_succeeding,
+    composition::composition_swarm::{canonical_order, synthetic_command},
     machine::{Error, Side},
 };
 use itertools::Itertools;
@@ -18,7 +19,7 @@ use petgraph::{
 };
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
 };
 // types more or less copied from machine.rs.
 type Graph = petgraph::Graph<State, MachineLabel>;
@@ -137,7 +138,7 @@ pub fn project(
     }
 }
 
-// Map the protocols of a proto_info to a ChainedProtos
+// Map the protocols of a proto_info to a ChainedProtos, chained in canonical order
 fn to_chained_protos(proto_info: &ProtoInfo) -> ChainedProtos {
     let folder = |(acc, roles_prev): (ChainedProtos, BTreeSet<Role>),
                   proto: ProtoStruct|
@@ -160,10 +161,9 @@ fn to_chained_protos(proto_info: &ProtoInfo) -> ChainedProtos {
 
         (acc,proto.roles.union(&roles_prev).cloned().collect())
     };
-    let (chained_protos, _) = proto_info
-        .protocols
-        .clone()
+    let (chained_protos, _) = canonical_order(&proto_info.protocols)
         .into_iter()
+        .map(|i| proto_info.protocols[i].clone())
         .fold((vec![], BTreeSet::new()), folder);
     chained_protos
 }
@@ -202,7 +202,10 @@ pub fn project_combine(
         // option because used in equivalent. Consider changing.
         {
             (
-                to_option_machine(&combined_projection),
+                fold_desugared_commands(
+                    to_option_machine(&combined_projection),
+                    &proto_info.desugared_commands,
+                ),
                 Some(combined_initial),
             )
         }
@@ -210,7 +213,57 @@ pub fn project_combine(
     }
 }
 
-fn combine_projs<N: Clone, E: Clone + EventLabel>(
+// Fold the steps of transitions desugared by swarm_to_graph back into one command:
+// execute transitions of a desugared command get the full log type
+// and the execute transitions of the synthetic commands are removed.
+pub fn fold_desugared_commands<N: Clone>(
+    machine: petgraph::Graph<N, MachineLabel>,
+    desugared_commands: &BTreeMap<Command, Vec<EventType>>,
+) -> petgraph::Graph<N, MachineLabel> {
+    let _span = tracing::info_span!("fold_desugared_commands").entered();
+    if desugared_commands.is_empty() {
+        return machine;
+    }
+    let synthetic: BTreeSet<Command> = desugared_commands
+        .iter()
+        .flat_map(|(cmd, log_type)| (1..log_type.len()).map(|i| synthetic_command(cmd, i)))
+        .collect();
+    machine.filter_map(
+        |_, n| Some(n.clone()),
+        |_, label| match label {
+            MachineLabel::Execute { cmd, .. } if synthetic.contains(cmd) => None,
+            MachineLabel::Execute { cmd, .. } if desugared_commands.contains_key(cmd) => {
+                Some(MachineLabel::Execute {
+                    cmd: cmd.clone(),
+                    log_type: desugared_commands[cmd].clone(),
+                })
+            }
+            _ => Some(label.clone()),
+        },
+    )
+}
+
+// Inverse of fold_desugared_commands for a machine to be compared with an unfolded projection:
+// execute transitions of a desugared command get the log type of the first step.
+fn unfold_desugared_commands(
+    machine: OptionGraph,
+    desugared_commands: &BTreeMap<Command, Vec<EventType>>,
+) -> OptionGraph {
+    machine.map(
+        |_, n| n.clone(),
+        |_, label| match label {
+            MachineLabel::Execute { cmd, log_type } if desugared_commands.contains_key(cmd) => {
+                MachineLabel::Execute {
+                    cmd: cmd.clone(),
+                    log_type: log_type.iter().take(1).cloned().collect(),
+                }
+            }
+            _ => label.clone(),
+        },
+    )
+}
+
+fn combine_projs<N: Clone + Sync, E: EventLabel + Send + Sync>(
     projections: Vec<(petgraph::Graph<N, E>, NodeId, BTreeSet<EventType>)>,
     gen_node: fn(&N, &N) -> N,
 ) -> Option<(petgraph::Graph<N, E>, NodeId)> {
@@ -226,6 +279,66 @@ fn combine_projs<N: Clone, E: Clone + EventLabel>(
     Some((combined_projection, combined_initial))
 }
 
+// Deterministic machine accepting the same sequences of transitions as machine.
+pub fn determinize(machine: &OptionGraph, initial: NodeId) -> (OptionGraph, NodeId) {
+    let (dfa, dfa_initial) = nfa_to_dfa(from_option_graph_to_graph(machine), initial);
+    (to_option_machine(&dfa), dfa_initial)
+}
+
+// Deterministic machine with the fewest states accepting the same sequences of transitions as machine.
+pub fn minimize(machine: &OptionGraph, initial: NodeId) -> (OptionGraph, NodeId) {
+    let (dfa, dfa_initial) = nfa_to_dfa(from_option_graph_to_graph(machine), initial);
+    let (minimal, minimal_initial) = minimal_machine(&dfa, dfa_initial);
+    (to_option_machine(&minimal), minimal_initial)
+}
+
+// Decide whether two machines accept the same sequences of transitions. Every state of a machine is accepting,
+// so the determinized machines are explored in lockstep until a transition is possible in only one of them.
+// None if the machines are equivalent, otherwise a shortest sequence telling them apart.
+pub fn language_equivalent(
+    left: &OptionGraph,
+    li: NodeId,
+    right: &OptionGraph,
+    ri: NodeId,
+) -> Option<LanguageDifference> {
+    let _span = tracing::info_span!("language_equivalent").entered();
+    let (left, li) = nfa_to_dfa(from_option_graph_to_graph(left), li);
+    let (right, ri) = nfa_to_dfa(from_option_graph_to_graph(right), ri);
+    let outgoing = |graph: &Graph, node: NodeId| -> BTreeMap<MachineLabel, NodeId> {
+        graph
+            .edges_directed(node, Outgoing)
+            .map(|e| (e.weight().clone(), e.target()))
+            .collect()
+    };
+
+    let mut queue = VecDeque::from([(li, ri, vec![])]);
+    let mut visited = BTreeSet::from([(li, ri)]);
+    while let Some((li, ri, trace)) = queue.pop_front() {
+        let l_out = outgoing(&left, li);
+        let r_out = outgoing(&right, ri);
+        let only_left = l_out.keys().find(|label| !r_out.contains_key(*label));
+        let only_right = r_out.keys().find(|label| !l_out.contains_key(*label));
+        if let Some((label, in_left)) = only_left
+            .map(|label| (label, true))
+            .or(only_right.map(|label| (label, false)))
+        {
+            let mut trace = trace;
+            trace.push(label.clone());
+            return Some(LanguageDifference { trace, in_left });
+        }
+        for (label, lt) in l_out {
+            let rt = r_out[&label];
+            if visited.insert((lt, rt)) {
+                let mut trace = trace.clone();
+                trace.push(label);
+                queue.push_back((lt, rt, trace));
+            }
+        }
+    }
+
+    None
+}
+
 // nfa to dfa using subset construction. Hopcroft, Motwani and Ullman section 2.3.5
 fn nfa_to_dfa(nfa: Graph, i: NodeId) -> (Graph, NodeId) {
     let _span = tracing::info_span!("nfa_to_dfa").entered();
@@ -425,7 +538,9 @@ pub fn paths_from_event_types(proj: &OptionGraph, proto_info: &ProtoInfo) -> Bra
 
     // The reason for making set of concurrent events smaller is?
     let after_pairs: BTreeSet<UnordEventPair> =
-        transitive_closure_succeeding(proto_info.succeeding_events.clone())
+        proto_info
+            .succeeding_events_closure
+            .clone()
             .into_iter()
             .map(|(e, es)| {
                 [e].into_iter()
@@ -469,7 +584,7 @@ pub fn paths_from_event_types(proj: &OptionGraph, proto_info: &ProtoInfo) -> Bra
 // precondition: both machines are projected from wwf protocols?
 // precondition: m1 and m2 subscribe to all events in interface? Sort of works without but not really?
 // takes type parameters to make it work for machines and protocols.
-pub(in crate::composition) fn compose<N, E: EventLabel>(
+pub(in crate::composition) fn compose<N: Sync, E: EventLabel + Send + Sync>(
     m1: petgraph::Graph<N, E>,
     i1: NodeId,
     m2: petgraph::Graph<N, E>,
@@ -478,9 +593,28 @@ pub(in crate::composition) fn compose<N, E: EventLabel>(
     gen_node: fn(&N, &N) -> N,
 ) -> (petgraph::Graph<N, E>, NodeId) {
     let _span = tracing::info_span!("compose").entered();
-    let mut machine = petgraph::Graph::<N, E>::new();
-    let mut node_map: BTreeMap<(NodeId, NodeId), NodeId> = BTreeMap::new();
 
+    // with the parallel feature the reachable states of the product and their outgoing edges
+    // are computed up front by several threads. the product is then built exactly as without.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    {
+        let mut explored = parallel::explore_product(&m1, i1, &m2, i2, &interface);
+        build_product(&m1, i1, &m2, i2, gen_node, |s| explored.remove(&s).unwrap())
+    }
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    build_product(&m1, i1, &m2, i2, gen_node, |s| {
+        product_successors(&m1, &m2, &interface, s)
+    })
+}
+
+// The outgoing edges of the state (s1, s2) of the product of m1 and m2 and the states they lead to.
+// Only visit edges that are not interfacing or interfacing and both outgoing of s1 and s2.
+fn product_successors<N, E: EventLabel>(
+    m1: &petgraph::Graph<N, E>,
+    m2: &petgraph::Graph<N, E>,
+    interface: &BTreeSet<EventType>,
+    (s1, s2): (NodeId, NodeId),
+) -> Vec<(E, (NodeId, NodeId))> {
     let weight_target_mapper = |e: EdgeReference<'_, E>| (e.weight().clone(), e.target());
 
     let outgoing_map = |m: &petgraph::Graph<N, E>, src: NodeId| -> BTreeMap<E, NodeId> {
@@ -496,45 +630,56 @@ pub(in crate::composition) fn compose<N, E: EventLabel>(
             .partition(|e| interface.contains(&e.get_event_type()))
     };
 
-    let outgoing_to_visit = |m1: &petgraph::Graph<N, E>,
-                             s1: NodeId,
-                             m2: &petgraph::Graph<N, E>,
-                             s2: NodeId|
-     -> Vec<E> {
-        let (interfacing1, non_interfacing1) = partitioned(m1, s1);
-        let (interfacing2, non_interfacing2) = partitioned(m2, s2);
+    let map1 = outgoing_map(m1, s1);
+    let map2 = outgoing_map(m2, s2);
+    let (interfacing1, non_interfacing1) = partitioned(m1, s1);
+    let (interfacing2, non_interfacing2) = partitioned(m2, s2);
 
-        let interfacing_in_both: Vec<E> = interfacing1
-            .iter()
-            .cloned()
-            .collect::<BTreeSet<E>>()
-            .intersection(&interfacing2.iter().cloned().collect::<BTreeSet<E>>())
-            .cloned()
-            .collect();
-        vec![non_interfacing1, non_interfacing2, interfacing_in_both]
-            .into_iter()
-            .flatten()
-            .collect()
-    };
+    let interfacing_in_both: Vec<E> = interfacing1
+        .iter()
+        .cloned()
+        .collect::<BTreeSet<E>>()
+        .intersection(&interfacing2.iter().cloned().collect::<BTreeSet<E>>())
+        .cloned()
+        .collect();
+
+    vec![non_interfacing1, non_interfacing2, interfacing_in_both]
+        .into_iter()
+        .flatten()
+        .map(|e| {
+            let dst = match (map1.get(&e), map2.get(&e)) {
+                (Some(e1), Some(e2)) => (*e1, *e2),
+                (Some(e1), None) => (*e1, s2),
+                (None, Some(e2)) => (s1, *e2),
+                // every label was taken from the outgoing edges of s1 or s2, so it is in map1 or map2
+                (None, None) => unreachable!("label is not outgoing of either state"),
+            };
+            (e, dst)
+        })
+        .collect()
+}
+
+// Build the product of m1 and m2 in depth-first order starting from (i1, i2),
+// with successors giving the outgoing edges of each state of the product.
+fn build_product<N, E: EventLabel>(
+    m1: &petgraph::Graph<N, E>,
+    i1: NodeId,
+    m2: &petgraph::Graph<N, E>,
+    i2: NodeId,
+    gen_node: fn(&N, &N) -> N,
+    mut successors: impl FnMut((NodeId, NodeId)) -> Vec<(E, (NodeId, NodeId))>,
+) -> (petgraph::Graph<N, E>, NodeId) {
+    let mut machine = petgraph::Graph::<N, E>::new();
+    let mut node_map: BTreeMap<(NodeId, NodeId), NodeId> = BTreeMap::new();
 
     let combined_initial = machine.add_node(gen_node(&m1[i1], &m2[i2]));
     node_map.insert((i1, i2), combined_initial);
     let mut worklist = vec![(combined_initial, (i1, i2))];
 
-    while let Some((src, (old_src1, old_src2))) = worklist.pop() {
-        let map1 = outgoing_map(&m1, old_src1);
-        let map2 = outgoing_map(&m2, old_src2);
-        let outgoing_edges = outgoing_to_visit(&m1, old_src1, &m2, old_src2);
-
-        // add all outgoing edges from src node. only visit edges that are not interfacing or interfacing and both outgoing of old_src1 and old_src2
+    while let Some((src, old_src)) = worklist.pop() {
+        // add all outgoing edges from src node.
         // if a edge leads to a node that does not exist yet, create the node.
-        for e in outgoing_edges {
-            let (dst1, dst2) = match (map1.get(&e), map2.get(&e)) {
-                (Some(e1), Some(e2)) => (*e1, *e2),
-                (Some(e1), None) => (*e1, old_src2),
-                (None, Some(e2)) => (old_src1, *e2),
-                _ => unimplemented!(),
-            };
+        for (e, (dst1, dst2)) in successors(old_src) {
             if node_map.contains_key(&(dst1, dst2)) {
                 let dst = node_map.get(&(dst1, dst2)).unwrap();
                 machine.add_edge(src, *dst, e);
@@ -550,6 +695,81 @@ pub(in crate::composition) fn compose<N, E: EventLabel>(
     (machine, combined_initial)
 }
 
+// Exploration of the states of a product using several threads.
+// The frontier of each breadth-first level is split among the threads and
+// the visited states are kept in a map split into shards with a lock each.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+mod parallel {
+    use super::{product_successors, EventLabel, EventType, NodeId};
+    use rayon::prelude::*;
+    use std::collections::{hash_map::Entry, BTreeSet, HashMap};
+    use std::sync::Mutex;
+
+    const SHARDS: usize = 64;
+    // levels with fewer states than this are explored by the calling thread
+    const MIN_PARALLEL_FRONTIER: usize = 32;
+
+    type Successors<E> = Vec<(E, (NodeId, NodeId))>;
+    // None marks a state that has been discovered but not explored yet.
+    type Shard<E> = Mutex<HashMap<(NodeId, NodeId), Option<Successors<E>>>>;
+
+    fn shard(s: &(NodeId, NodeId)) -> usize {
+        (s.0.index().wrapping_mul(31) ^ s.1.index()) % SHARDS
+    }
+
+    // Map each reachable state of the product of m1 and m2 to its outgoing edges, see product_successors().
+    pub(super) fn explore_product<N: Sync, E: EventLabel + Send + Sync>(
+        m1: &petgraph::Graph<N, E>,
+        i1: NodeId,
+        m2: &petgraph::Graph<N, E>,
+        i2: NodeId,
+        interface: &BTreeSet<EventType>,
+    ) -> HashMap<(NodeId, NodeId), Successors<E>> {
+        let _span = tracing::info_span!("explore_product").entered();
+        let shards: Vec<Shard<E>> = (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect();
+        shards[shard(&(i1, i2))]
+            .lock()
+            .unwrap()
+            .insert((i1, i2), None);
+
+        // explore a state, returning the states discovered for the first time.
+        let visit = |s: &(NodeId, NodeId)| -> Vec<(NodeId, NodeId)> {
+            let successors = product_successors(m1, m2, interface, *s);
+            let discovered = successors
+                .iter()
+                .filter(|(_, dst)| match shards[shard(dst)].lock().unwrap().entry(*dst) {
+                    Entry::Occupied(_) => false,
+                    Entry::Vacant(entry) => {
+                        entry.insert(None);
+                        true
+                    }
+                })
+                .map(|(_, dst)| *dst)
+                .collect();
+            shards[shard(s)]
+                .lock()
+                .unwrap()
+                .insert(*s, Some(successors));
+            discovered
+        };
+
+        let mut frontier = vec![(i1, i2)];
+        while !frontier.is_empty() {
+            frontier = if frontier.len() < MIN_PARALLEL_FRONTIER {
+                frontier.iter().flat_map(visit).collect()
+            } else {
+                frontier.par_iter().flat_map_iter(visit).collect()
+            };
+        }
+
+        shards
+            .into_iter()
+            .flat_map(|shard| shard.into_inner().unwrap())
+            .map(|(s, successors)| (s, successors.unwrap()))
+            .collect()
+    }
+}
+
 pub fn gen_state_name<N: StateName + From<String>>(n1: &N, n2: &N) -> N {
     let name = format!("{} || {}", n1.state_name(), n2.state_name());
     N::from(name)
@@ -721,6 +941,11 @@ fn adapted_projection(
             .into_iter()
             .map(mapper)
             .collect();
+    // position of the kth protocol in the chain
+    let k = canonical_order(&proto_info.protocols)
+        .into_iter()
+        .position(|i| i == k)
+        .unwrap();
     
     //AdaptationGraph{state: n.clone(), machine_state: Some(state.clone())}
     let (machine, machine_initial) = (from_option_graph_to_graph(&machine.0), machine.1);
@@ -787,6 +1012,10 @@ pub fn projection_information(
     k: usize,
     minimize: bool,
 ) -> Option<ProjectionInfo> {
+    let machine = (
+        unfold_desugared_commands(machine.0, &proto_info.desugared_commands),
+        machine.1,
+    );
     let (proj, proj_initial) =
         match adapted_projection(&proto_info, subs, role, machine, k, minimize) {
             Some((proj, Some(proj_initial))) => (proj, proj_initial),
@@ -803,7 +1032,10 @@ pub fn projection_information(
         })
         .collect();
 
-    let proj = from_adaptation_graph_to_option_graph(&proj);
+    let proj = fold_desugared_commands(
+        from_adaptation_graph_to_option_graph(&proj),
+        &proto_info.desugared_commands,
+    );
 
     let branches = paths_from_event_types(&proj, &proto_info);
     let special_event_types = get_branching_joining_proto_info(&proto_info);
@@ -890,6 +1122,7 @@ mod tests {
     use super::*;
     use crate::{
         composition::{
+            composition_fixtures::{get_interfacing_swarms_1, get_proto1, get_proto2},
             composition_swarm::{
                 compose_protocols, exact_well_formed_sub, from_json,
                 overapprox_well_formed_sub, swarms_to_proto_info,
@@ -920,33 +1153,6 @@ mod tests {
         graph.map(|_, n| n.state.state_name().clone(), |_, x| x.clone())
     }
 
-    fn get_proto1() -> SwarmProtocolType {
-        serde_json::from_str::<SwarmProtocolType>(
-            r#"{
-                "initial": "0",
-                "transitions": [
-                    { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
-                    { "source": "1", "target": "2", "label": { "cmd": "get", "logType": ["pos"], "role": "FL" } },
-                    { "source": "2", "target": "0", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
-                    { "source": "0", "target": "3", "label": { "cmd": "close", "logType": ["time"], "role": "D" } }
-                ]
-            }"#,
-        )
-        .unwrap()
-    }
-    fn get_proto2() -> SwarmProtocolType {
-        serde_json::from_str::<SwarmProtocolType>(
-            r#"{
-                "initial": "0",
-                "transitions": [
-                    { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
-                    { "source": "1", "target": "2", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
-                    { "source": "2", "target": "3", "label": { "cmd": "build", "logType": ["car"], "role": "F" } }
-                ]
-            }"#,
-        )
-        .unwrap()
-    }
     fn get_proto3() -> SwarmProtocolType {
         serde_json::from_str::<SwarmProtocolType>(
             r#"{
@@ -975,10 +1181,6 @@ mod tests {
         .unwrap()
     }
 
-    fn get_interfacing_swarms_1() -> InterfacingProtocols {
-        InterfacingProtocols(vec![get_proto1(), get_proto2()])
-    }
-
     fn get_interfacing_swarms_1_reversed() -> InterfacingProtocols {
         InterfacingProtocols(vec![get_proto2(), get_proto1()])
     }
@@ -2094,6 +2296,110 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_project_combine_multiple_interfacing_roles() {
+            setup_logger();
+            // The protocols share the roles T and D
+            let protos = InterfacingProtocols(vec![
+                serde_json::from_str::<SwarmProtocolType>(
+                    r#"{
+                        "initial": "0",
+                        "transitions": [
+                            { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                            { "source": "1", "target": "2", "label": { "cmd": "get", "logType": ["pos"], "role": "FL" } },
+                            { "source": "2", "target": "0", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+                            { "source": "0", "target": "3", "label": { "cmd": "close", "logType": ["time"], "role": "D" } }
+                        ]
+                    }"#,
+                )
+                .unwrap(),
+                serde_json::from_str::<SwarmProtocolType>(
+                    r#"{
+                        "initial": "0",
+                        "transitions": [
+                            { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                            { "source": "1", "target": "2", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+                            { "source": "2", "target": "0", "label": { "cmd": "build", "logType": ["car"], "role": "F" } },
+                            { "source": "0", "target": "3", "label": { "cmd": "close", "logType": ["time"], "role": "D" } }
+                        ]
+                    }"#,
+                )
+                .unwrap(),
+            ]);
+            let (composed_graph, composed_initial) = compose_protocols(protos.clone()).unwrap();
+            let subs = exact_well_formed_sub(protos.clone(), &BTreeMap::new()).unwrap();
+            let proto_info = swarms_to_proto_info(protos);
+
+            for role in ["T", "FL", "D", "F"].map(Role::new) {
+                let (expand_proj, expand_proj_initial) =
+                    project(&composed_graph, composed_initial, &subs, role.clone(), true);
+                let (combined_proj, combined_proj_initial) =
+                    project_combine(&proto_info, &subs, role, true);
+                assert!(equivalent(
+                    &to_option_machine(&expand_proj),
+                    expand_proj_initial,
+                    &combined_proj,
+                    combined_proj_initial.unwrap()
+                )
+                .is_empty());
+            }
+        }
+
+        #[test]
+        fn test_project_combine_multi_event() {
+            setup_logger();
+            let protos = InterfacingProtocols(vec![
+                serde_json::from_str::<SwarmProtocolType>(
+                    r#"{
+                        "initial": "0",
+                        "transitions": [
+                            { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                            { "source": "1", "target": "2", "label": { "cmd": "deliver", "logType": ["part", "receipt"], "role": "T" } }
+                        ]
+                    }"#,
+                )
+                .unwrap(),
+                serde_json::from_str::<SwarmProtocolType>(
+                    r#"{
+                        "initial": "0",
+                        "transitions": [
+                            { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                            { "source": "1", "target": "2", "label": { "cmd": "deliver", "logType": ["part", "receipt"], "role": "T" } },
+                            { "source": "2", "target": "3", "label": { "cmd": "build", "logType": ["car"], "role": "F" } }
+                        ]
+                    }"#,
+                )
+                .unwrap(),
+            ]);
+            let subs = exact_well_formed_sub(protos.clone(), &BTreeMap::new()).unwrap();
+            let proto_info = swarms_to_proto_info(protos);
+            let (proj, proj_initial) = project_combine(&proto_info, &subs, Role::new("T"), true);
+
+            // the synthetic step emitting receipt is folded into deliver
+            let expected = serde_json::from_str::<MachineType>(
+                r#"{
+                    "initial": "0",
+                    "transitions": [
+                        { "source": "0", "target": "0", "label": { "tag": "Execute", "cmd": "request", "logType": ["partID"] } },
+                        { "source": "0", "target": "1", "label": { "tag": "Input", "eventType": "partID" } },
+                        { "source": "1", "target": "1", "label": { "tag": "Execute", "cmd": "deliver", "logType": ["part", "receipt"] } },
+                        { "source": "1", "target": "1a", "label": { "tag": "Input", "eventType": "part" } },
+                        { "source": "1a", "target": "2", "label": { "tag": "Input", "eventType": "receipt" } }
+                    ]
+                }"#,
+            )
+            .unwrap();
+            let (expected, expected_initial, errors) = crate::machine::from_json(expected);
+            assert!(errors.is_empty());
+            assert!(equivalent(
+                &expected,
+                expected_initial.unwrap(),
+                &proj,
+                proj_initial.unwrap()
+            )
+            .is_empty());
+        }
+
         #[test]
         fn test_compose_zero() {
             let left = MachineType {
@@ -2587,6 +2893,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_minimize_and_language_equivalent() {
+        let parse = |json: &str| {
+            let (graph, initial, errors) =
+                machine::from_json(serde_json::from_str::<MachineType>(json).unwrap());
+            assert!(errors.is_empty());
+            (graph, initial.unwrap())
+        };
+        let (machine, initial) = parse(
+            r#"{
+                "initial": "0",
+                "transitions": [
+                    { "source": "0", "target": "0", "label": { "tag": "Execute", "cmd": "go", "logType": ["a"] } },
+                    { "source": "0", "target": "1", "label": { "tag": "Input", "eventType": "a" } },
+                    { "source": "0", "target": "2", "label": { "tag": "Input", "eventType": "a" } },
+                    { "source": "1", "target": "3", "label": { "tag": "Input", "eventType": "b" } },
+                    { "source": "2", "target": "4", "label": { "tag": "Input", "eventType": "b" } }
+                ]
+            }"#,
+        );
+        let (dfa, dfa_initial) = determinize(&machine, initial);
+        assert_eq!(dfa.node_count(), 3);
+        let (minimal, minimal_initial) = minimize(&machine, initial);
+        assert_eq!(minimal.node_count(), 3);
+        assert_eq!(
+            language_equivalent(&machine, initial, &dfa, dfa_initial),
+            None
+        );
+        assert_eq!(
+            language_equivalent(&machine, initial, &minimal, minimal_initial),
+            None
+        );
+
+        let (other, other_initial) = parse(
+            r#"{
+                "initial": "0",
+                "transitions": [
+                    { "source": "0", "target": "0", "label": { "tag": "Execute", "cmd": "go", "logType": ["a"] } },
+                    { "source": "0", "target": "1", "label": { "tag": "Input", "eventType": "a" } },
+                    { "source": "1", "target": "2", "label": { "tag": "Input", "eventType": "c" } }
+                ]
+            }"#,
+        );
+        assert_eq!(
+            language_equivalent(&machine, initial, &other, other_initial),
+            Some(LanguageDifference {
+                trace: vec![
+                    MachineLabel::Input {
+                        event_type: EventType::new("a")
+                    },
+                    MachineLabel::Input {
+                        event_type: EventType::new("b")
+                    }
+                ],
+                in_left: true
+            })
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_det_proj_1() {
@@ -3492,4 +3857,76 @@ mod tests {
             }
         }
     }
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_parallel_exploration() {
+        setup_logger();
+        // protocols synchronizing on s and then looping independently
+        let proto = |k: usize| -> SwarmProtocolType {
+            let transitions = (1..8)
+                .map(|j| {
+                    format!(
+                        r#"{{ "source": "{j}", "target": "{}", "label": {{ "cmd": "c{k}_{j}", "logType": ["e{k}_{j}"], "role": "R{k}" }} }}"#,
+                        j % 7 + 1
+                    )
+                })
+                .join(", ");
+            serde_json::from_str::<SwarmProtocolType>(&format!(
+                r#"{{
+                    "initial": "0",
+                    "transitions": [
+                        {{ "source": "0", "target": "1", "label": {{ "cmd": "s", "logType": ["s"], "role": "S" }} }},
+                        {transitions}
+                    ]
+                }}"#
+            ))
+            .unwrap()
+        };
+        let proto_info = swarms_to_proto_info(InterfacingProtocols((0..4).map(proto).collect()));
+        assert!(proto_info.no_errors());
+        // fold compose over the protocols as explicit_composition does, exploring in parallel or not
+        let composition = |parallel: bool| -> crate::Graph {
+            let (g, i, _) = proto_info.protocols[0].get_triple();
+            let (g, _) = proto_info.protocols[1..]
+                .iter()
+                .fold((g, i.unwrap()), |(acc, acc_i), p| {
+                    let interface = BTreeSet::from([EventType::new("s")]);
+                    if parallel {
+                        let mut explored = parallel::explore_product(
+                            &acc,
+                            acc_i,
+                            &p.graph,
+                            p.initial.unwrap(),
+                            &interface,
+                        );
+                        build_product(&acc, acc_i, &p.graph, p.initial.unwrap(), gen_state_name, |s| {
+                            explored.remove(&s).unwrap()
+                        })
+                    } else {
+                        build_product(&acc, acc_i, &p.graph, p.initial.unwrap(), gen_state_name, |s| {
+                            product_successors(&acc, &p.graph, &interface, s)
+                        })
+                    }
+                });
+            g
+        };
+        let sequential = composition(false);
+        let parallel = composition(true);
+        assert_eq!(sequential.node_count(), 7 * 7 * 7 * 7 + 1);
+        assert_eq!(
+            sequential.node_weights().collect::<Vec<_>>(),
+            parallel.node_weights().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            sequential
+                .edge_references()
+                .map(|e| (e.source(), e.target(), e.weight()))
+                .collect::<Vec<_>>(),
+            parallel
+                .edge_references()
+                .map(|e| (e.source(), e.target(), e.weight()))
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -0,0 +1,259 @@
+use std::collections::{hash_map::DefaultHasher, BTreeSet, HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use super::composition_swarm::{proto_info_to_error_report, swarms_to_proto_info, ErrorReport};
+use super::composition_swarm_simulation::{insert, next_lamport, Event, Swarm};
+use super::composition_types::{
+    Counterexample, DeliveryModel, InterfacingProtocols, ModelCheckConfig, ModelCheckReport,
+    ScheduleStep,
+};
+use crate::Subscriptions;
+
+/*
+ * A state of the swarm: the events emitted so far, the local log of every replica and the events
+ * still to be delivered as pairs (replica, event). causes[e] is the causal past of event e: the events in
+ * the local log of its emitter when it was emitted and their causal pasts.
+ */
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Configuration {
+    events: Vec<Event>,
+    causes: Vec<BTreeSet<usize>>,
+    logs: Vec<Vec<usize>>,
+    pending: BTreeSet<(usize, usize)>,
+}
+
+impl Configuration {
+    fn hash_value(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn deliverable(&self, delivery: DeliveryModel, to: usize, event: usize) -> bool {
+        match delivery {
+            DeliveryModel::Arbitrary => true,
+            DeliveryModel::Fifo => !self.pending.iter().any(|(r, e)| {
+                *r == to && *e < event && self.events[*e].role == self.events[event].role
+            }),
+            DeliveryModel::Causal => !self
+                .pending
+                .iter()
+                .any(|(r, e)| *r == to && self.causes[event].contains(e)),
+        }
+    }
+
+    // Every command enabled for some replica within the bound on events and every delivery allowed by the delivery model.
+    fn successors(
+        &self,
+        swarm: &Swarm,
+        config: &ModelCheckConfig,
+    ) -> Vec<(ScheduleStep, Configuration)> {
+        let mut successors = vec![];
+        for (r, replica) in swarm.replicas.iter().enumerate() {
+            let enabled: BTreeSet<_> = replica
+                .enabled(&self.events, &self.logs[r])
+                .into_iter()
+                .filter(|(_, log_type)| self.events.len() + log_type.len() <= config.max_events)
+                .collect();
+            for (cmd, log_type) in enabled {
+                let mut next = self.clone();
+                let mut ids = vec![];
+                for event_type in log_type {
+                    let id = next.events.len();
+                    let causes = next.logs[r]
+                        .iter()
+                        .flat_map(|e| next.causes[*e].iter().chain([e]))
+                        .cloned()
+                        .collect();
+                    next.events.push(Event {
+                        event_type: event_type.clone(),
+                        role: r,
+                        lamport: next_lamport(&next.events, &next.logs[r]),
+                    });
+                    next.causes.push(causes);
+                    insert(&next.events, &mut next.logs[r], id);
+                    for (to, subscriber) in swarm.replicas.iter().enumerate() {
+                        if to != r && subscriber.subscription.contains(&event_type) {
+                            next.pending.insert((to, id));
+                        }
+                    }
+                    ids.push(id);
+                }
+                let step = ScheduleStep::Emit {
+                    role: replica.role.clone(),
+                    cmd,
+                    events: ids,
+                };
+                successors.push((step, next));
+            }
+        }
+        for (to, event) in &self.pending {
+            if self.deliverable(config.delivery, *to, *event) {
+                let mut next = self.clone();
+                next.pending.remove(&(*to, *event));
+                insert(&next.events, &mut next.logs[*to], *event);
+                let step = ScheduleStep::Deliver {
+                    event: *event,
+                    to: swarm.replicas[*to].role.clone(),
+                };
+                successors.push((step, next));
+            }
+        }
+        successors
+    }
+}
+
+/*
+ * Explore every interleaving of emissions and deliveries of a swarm running the composition of protos, where
+ * each role executes the commands enabled by its projection (project_combine(), minimized) run over its local log
+ * and events reach the subscribers of their event types as allowed by config.delivery. Whenever every event has been
+ * delivered, the roles must agree with the composition in the sense of composition_swarm_simulation::simulate_swarm().
+ * States are explored breadth first, so a counterexample is a shortest schedule leading to a disagreement.
+ * Exploration stops at the first counterexample, so the report is only complete if there is none.
+ */
+pub fn model_check(
+    protos: InterfacingProtocols,
+    subs: &Subscriptions,
+    config: ModelCheckConfig,
+) -> Result<ModelCheckReport, ErrorReport> {
+    let _span = tracing::info_span!("model_check").entered();
+    let proto_info = swarms_to_proto_info(protos);
+    if !proto_info.no_errors() {
+        return Err(proto_info_to_error_report(proto_info));
+    }
+    let Some(swarm) = Swarm::new(&proto_info, subs) else {
+        return Ok(ModelCheckReport {
+            states: 0,
+            complete: true,
+            counterexample: None,
+        });
+    };
+
+    let initial = Configuration {
+        events: vec![],
+        causes: vec![],
+        logs: vec![vec![]; swarm.replicas.len()],
+        pending: BTreeSet::new(),
+    };
+    // The indices in states of the states reached, by the hash of their configuration.
+    let mut visited: HashMap<u64, Vec<usize>> = HashMap::from([(initial.hash_value(), vec![0])]);
+    // Every state reached with the index of its predecessor and the step leading to it.
+    let mut states: Vec<(Configuration, Option<(usize, ScheduleStep)>)> = vec![(initial, None)];
+    let mut queue = VecDeque::from([0]);
+    while let Some(i) = queue.pop_front() {
+        let configuration = &states[i].0;
+        if configuration.pending.is_empty() {
+            let (log, disagreements) = swarm.outcome(&configuration.events, &configuration.logs);
+            if !disagreements.is_empty() {
+                let mut schedule = vec![];
+                let mut current = i;
+                while let Some((previous, step)) = &states[current].1 {
+                    schedule.push(step.clone());
+                    current = *previous;
+                }
+                schedule.reverse();
+                return Ok(ModelCheckReport {
+                    states: states.len(),
+                    complete: false,
+                    counterexample: Some(Counterexample {
+                        schedule,
+                        log,
+                        disagreements,
+                    }),
+                });
+            }
+        }
+        for (step, next) in configuration.successors(&swarm, &config) {
+            if states.len() >= config.max_states {
+                return Ok(ModelCheckReport {
+                    states: states.len(),
+                    complete: false,
+                    counterexample: None,
+                });
+            }
+            let indices = visited.entry(next.hash_value()).or_default();
+            if !indices.iter().any(|j| states[*j].0 == next) {
+                indices.push(states.len());
+                queue.push_back(states.len());
+                states.push((next, Some((i, step))));
+            }
+        }
+    }
+
+    Ok(ModelCheckReport {
+        states: states.len(),
+        complete: true,
+        counterexample: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::composition_fixtures::{get_branching_proto, get_uninformed_branch_subs};
+    use crate::composition::composition_swarm::exact_well_formed_sub;
+    use crate::types::Role;
+    use std::collections::BTreeMap;
+
+    fn config(delivery: DeliveryModel) -> ModelCheckConfig {
+        ModelCheckConfig {
+            delivery,
+            max_events: 4,
+            max_states: 100000,
+        }
+    }
+
+    #[test]
+    fn test_model_check() {
+        let protos = InterfacingProtocols(vec![get_branching_proto()]);
+        let subs = exact_well_formed_sub(protos.clone(), &BTreeMap::new()).unwrap();
+        for delivery in [
+            DeliveryModel::Arbitrary,
+            DeliveryModel::Fifo,
+            DeliveryModel::Causal,
+        ] {
+            let report = model_check(protos.clone(), &subs, config(delivery)).unwrap();
+            assert!(report.complete);
+            assert!(report.states > 1);
+            assert_eq!(report.counterexample, None);
+        }
+
+        let subs = get_uninformed_branch_subs();
+        for delivery in [
+            DeliveryModel::Arbitrary,
+            DeliveryModel::Fifo,
+            DeliveryModel::Causal,
+        ] {
+            let report = model_check(protos.clone(), &subs, config(delivery)).unwrap();
+            assert!(!report.complete);
+            let counterexample = report.counterexample.unwrap();
+            // Both branches are taken concurrently and delivered to R3.
+            assert_eq!(counterexample.schedule.len(), 4);
+            assert_eq!(
+                counterexample
+                    .schedule
+                    .iter()
+                    .filter(|step| matches!(step, ScheduleStep::Emit { .. }))
+                    .count(),
+                2
+            );
+            assert!(counterexample
+                .disagreements
+                .iter()
+                .all(|d| d.branching && d.role != Role::new("R3")));
+        }
+
+        let report = model_check(
+            protos,
+            &subs,
+            ModelCheckConfig {
+                delivery: DeliveryModel::Arbitrary,
+                max_events: 4,
+                max_states: 2,
+            },
+        )
+        .unwrap();
+        assert!(!report.complete);
+        assert_eq!(report.counterexample, None);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use petgraph::{visit::EdgeRef, Direction::Outgoing};
+
+use super::composition_swarm::{
+    explicit_composition_proto_info, proto_info_to_error_report, swarms_to_proto_info, ErrorReport,
+};
+use super::composition_types::{
+    ConformanceReport, ConformanceViolation, ConformanceViolationKind, EventLabel,
+    InterfacingProtocols, LogEntry,
+};
+use crate::types::{EventType, Role, State, StateName};
+
+// Parse an event log given as JSON lines, one LogEntry per line. Blank lines are skipped.
+pub fn parse_log(log: &str) -> Result<Vec<LogEntry>, String> {
+    log.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str::<LogEntry>(line).map_err(|e| format!("line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+// Check that a recorded log could have been produced by the composition of protos: every event must be
+// emitted by the role emitting its event type in the protocols and be enabled in the expanded composition
+// after the events preceding it. Stops at the first violation.
+pub fn check_log(
+    protos: InterfacingProtocols,
+    log: &[LogEntry],
+) -> Result<ConformanceReport, ErrorReport> {
+    let _span = tracing::info_span!("check_log").entered();
+    let proto_info = swarms_to_proto_info(protos);
+    if !proto_info.no_errors() {
+        return Err(proto_info_to_error_report(proto_info));
+    }
+    let emitters: BTreeMap<EventType, Role> = proto_info
+        .role_event_map
+        .iter()
+        .flat_map(|(role, labels)| labels.iter().map(|l| (l.get_event_type(), role.clone())))
+        .collect();
+    let (graph, mut node) = match explicit_composition_proto_info(proto_info).get_ith_proto(0) {
+        Some(p) if p.initial.is_some() => (p.graph, p.initial.unwrap()),
+        _ => {
+            return Ok(ConformanceReport {
+                conforming: 0,
+                state: State::new(""),
+                violation: None,
+            })
+        }
+    };
+
+    for (index, entry) in log.iter().enumerate() {
+        let edge = graph
+            .edges_directed(node, Outgoing)
+            .find(|e| e.weight().get_event_type() == entry.event_type);
+        let kind = match (emitters.get(&entry.event_type), edge) {
+            (None, _) => Some(ConformanceViolationKind::UnknownEventType),
+            (Some(role), _) if *role != entry.role => Some(ConformanceViolationKind::WrongRole),
+            (_, None) => Some(ConformanceViolationKind::NotEnabled),
+            (_, Some(edge)) => {
+                node = edge.target();
+                None
+            }
+        };
+        if let Some(kind) = kind {
+            let mut allowed: Vec<_> = graph
+                .edges_directed(node, Outgoing)
+                .map(|e| e.weight().clone())
+                .collect();
+            allowed.sort();
+            let violation = ConformanceViolation {
+                index,
+                event_type: entry.event_type.clone(),
+                role: entry.role.clone(),
+                kind,
+                state: graph[node].state_name().clone(),
+                allowed,
+            };
+            return Ok(ConformanceReport {
+                conforming: index,
+                state: graph[node].state_name().clone(),
+                violation: Some(violation),
+            });
+        }
+    }
+
+    Ok(ConformanceReport {
+        conforming: log.len(),
+        state: graph[node].state_name().clone(),
+        violation: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::composition_fixtures::get_interfacing_swarms_1_done;
+
+    #[test]
+    fn test_check_log() {
+        let log = parse_log(
+            r#"{ "eventType": "partID", "role": "T", "payload": { "id": 1 } }
+               { "eventType": "pos", "role": "FL" }
+
+               { "eventType": "part", "role": "T" }
+               { "eventType": "car", "role": "F" }
+               { "eventType": "done", "role": "F" }
+               { "eventType": "time", "role": "D" }"#,
+        )
+        .unwrap();
+        assert_eq!(log.len(), 6);
+        let report = check_log(get_interfacing_swarms_1_done(), &log).unwrap();
+        assert_eq!(report.conforming, 6);
+        assert_eq!(report.violation, None);
+
+        // pos before partID is not enabled.
+        let report = check_log(get_interfacing_swarms_1_done(), &log[1..]).unwrap();
+        let violation = report.violation.unwrap();
+        assert_eq!(report.conforming, 0);
+        assert_eq!(violation.index, 0);
+        assert_eq!(violation.kind, ConformanceViolationKind::NotEnabled);
+        assert_eq!(
+            violation
+                .allowed
+                .iter()
+                .map(|label| label.get_event_type())
+                .collect::<Vec<_>>(),
+            vec![EventType::new("time"), EventType::new("partID")]
+        );
+
+        let mut wrong_role = log.clone();
+        wrong_role[1].role = Role::new("T");
+        let report = check_log(get_interfacing_swarms_1_done(), &wrong_role).unwrap();
+        assert_eq!(report.conforming, 1);
+        assert_eq!(
+            report.violation.unwrap().kind,
+            ConformanceViolationKind::WrongRole
+        );
+
+        let mut unknown = log.clone();
+        unknown[2].event_type = EventType::new("unknown");
+        let report = check_log(get_interfacing_swarms_1_done(), &unknown).unwrap();
+        assert_eq!(report.conforming, 2);
+        assert_eq!(
+            report.violation.unwrap().kind,
+            ConformanceViolationKind::UnknownEventType
+        );
+
+        assert!(parse_log("{ \"eventType\": \"partID\" }")
+            .unwrap_err()
+            .starts_with("line 1:"));
+    }
+}
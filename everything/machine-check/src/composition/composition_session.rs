@@ -0,0 +1,476 @@
+use std::collections::{hash_map::DefaultHasher, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+
+use wasm_bindgen::prelude::*;
+
+use super::composition_machine;
+use super::composition_swarm::{self, proto_info_to_error_report, ComposeStep, ErrorReport};
+use super::composition_types::{Granularity, InterfacingProtocols, ProtoInfo};
+use crate::types::{CheckResult, DataResult, ErrorCode, ErrorInfo, EventType, Role};
+use crate::{Graph, MachineType, NodeId, Subscriptions, SwarmProtocolType};
+
+/*
+ * A composition that is edited and checked repeatedly, e.g. by an editor on every keystroke.
+ * The proto info of every component, checked for confusion freeness, is cached under a hash of its content,
+ * so changing a component only prepares that component again. Likewise the interface errors of every pair of
+ * components are cached under the hashes of the two components, so only the interfaces of a changed component
+ * are checked again, and every step of the expanded composition is cached under the hashes of the components
+ * composed so far, so only the steps from the first one composing a changed component on are computed again.
+ * The combined proto info and the expanded composition are kept until a component changes and are then
+ * computed again from the cached parts, changing the subscription only reruns the checks.
+ */
+#[wasm_bindgen]
+#[derive(Debug, Default)]
+pub struct CompositionSession {
+    protocols: Vec<SwarmProtocolType>,
+    proto_infos: HashMap<u64, (SwarmProtocolType, ProtoInfo)>,
+    interface_errors: HashMap<(u64, u64), Vec<composition_swarm::Error>>,
+    composition_steps: HashMap<StepKey, (Graph, NodeId)>,
+    subscriptions: Subscriptions,
+    combined: Option<ProtoInfo>,
+    composition: Option<ProtoInfo>,
+}
+
+// The hashes of the components composed in a step of the expanded composition and the event types they were composed on.
+type StepKey = Vec<(u64, BTreeSet<EventType>)>;
+
+fn content_hash(proto: &SwarmProtocolType) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(proto).unwrap().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn invalid_index(k: usize) -> ErrorInfo {
+    ErrorInfo::new(ErrorCode::InvalidIndex, format!("invalid index {}", k))
+}
+
+#[wasm_bindgen]
+impl CompositionSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replace all components of the composition.
+    pub fn set_protocols(&mut self, protos: InterfacingProtocols) {
+        self.protocols = protos.0;
+        self.invalidate();
+    }
+
+    // Replace the kth component, or add a component if k is the number of components.
+    pub fn set_component(&mut self, k: usize, proto: SwarmProtocolType) -> CheckResult {
+        if k > self.protocols.len() {
+            return CheckResult::from_errors(vec![invalid_index(k)]);
+        }
+        if k == self.protocols.len() {
+            self.protocols.push(proto);
+        } else if self.protocols[k] != proto {
+            self.protocols[k] = proto;
+        } else {
+            return CheckResult::OK;
+        }
+        self.invalidate();
+        CheckResult::OK
+    }
+
+    pub fn remove_component(&mut self, k: usize) -> CheckResult {
+        if k >= self.protocols.len() {
+            return CheckResult::from_errors(vec![invalid_index(k)]);
+        }
+        self.protocols.remove(k);
+        self.invalidate();
+        CheckResult::OK
+    }
+
+    pub fn set_subscriptions(&mut self, subs: String) -> CheckResult {
+        match serde_json::from_str::<Subscriptions>(&subs) {
+            Ok(subs) => {
+                self.subscriptions = subs;
+                CheckResult::OK
+            }
+            Err(e) => CheckResult::from_errors(vec![ErrorInfo::invalid_subscriptions(e)]),
+        }
+    }
+
+    // Set the subscription of a single role. event_types is a JSON array of event types.
+    pub fn set_subscription(&mut self, role: Role, event_types: String) -> CheckResult {
+        match serde_json::from_str::<BTreeSet<EventType>>(&event_types) {
+            Ok(event_types) => {
+                self.subscriptions.insert(role, event_types);
+                CheckResult::OK
+            }
+            Err(e) => CheckResult::from_errors(vec![ErrorInfo::invalid_subscriptions(e)]),
+        }
+    }
+
+    pub fn check(&mut self) -> CheckResult {
+        let _span = tracing::info_span!("session_check").entered();
+        let subs = self.subscriptions.clone();
+        let error_report = match self.composition() {
+            Ok(composition) => proto_info_to_error_report(
+                composition_swarm::well_formed_proto_info(composition.clone(), &subs),
+            ),
+            Err(error_report) => error_report,
+        };
+        CheckResult::from_errors(error_report.to_infos())
+    }
+
+    pub fn exact_well_formed_sub(&mut self) -> DataResult<Subscriptions> {
+        let _span = tracing::info_span!("session_exact_well_formed_sub").entered();
+        let subs = self.subscriptions.clone();
+        match self.composition() {
+            Ok(composition) => DataResult::OK {
+                data: composition_swarm::exact_wf_sub(composition.clone(), 0, &subs),
+            },
+            Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+        }
+    }
+
+    pub fn overapproximated_well_formed_sub(
+        &mut self,
+        granularity: Granularity,
+    ) -> DataResult<Subscriptions> {
+        let _span = tracing::info_span!("session_overapprox_well_formed_sub").entered();
+        let subs = self.subscriptions.clone();
+        let combined = self.combined();
+        if !combined.no_errors() {
+            return DataResult::from_errors(
+                proto_info_to_error_report(combined.clone()).to_infos(),
+            );
+        }
+        DataResult::OK {
+            data: composition_swarm::overapprox_wf_sub(combined, &subs, granularity),
+        }
+    }
+
+    pub fn compose_protocols(&mut self) -> DataResult<SwarmProtocolType> {
+        match self.composition() {
+            Ok(composition) => {
+                let p = composition.get_ith_proto(0).unwrap();
+                DataResult::OK {
+                    data: composition_swarm::to_swarm_json(
+                        p.graph,
+                        p.initial.unwrap(),
+                        &composition.desugared_commands,
+                    ),
+                }
+            }
+            Err(error_report) => DataResult::from_errors(error_report.to_infos()),
+        }
+    }
+
+    pub fn project_combine(&mut self, role: Role, minimize: bool) -> DataResult<MachineType> {
+        let subs = self.subscriptions.clone();
+        let combined = self.combined();
+        if !combined.no_errors() {
+            return DataResult::from_errors(
+                proto_info_to_error_report(combined.clone()).to_infos(),
+            );
+        }
+        let (proj, proj_initial) =
+            composition_machine::project_combine(combined, &subs, role, minimize);
+        DataResult::OK {
+            data: composition_machine::from_option_to_machine(proj, proj_initial.unwrap()),
+        }
+    }
+}
+
+impl CompositionSession {
+    // Drop the combined proto info and the composition. The cached parts are kept, combined() drops those
+    // involving components that are no longer part of the composition.
+    fn invalidate(&mut self) {
+        self.combined = None;
+        self.composition = None;
+    }
+
+    // The proto info of a component, prepared only if no component with the same content was prepared before.
+    fn proto_info(&mut self, proto: &SwarmProtocolType) -> ProtoInfo {
+        let key = content_hash(proto);
+        match self.proto_infos.get(&key) {
+            Some((p, proto_info)) if p == proto => proto_info.clone(),
+            _ => {
+                let proto_info = composition_swarm::confusion_free_proto_info(
+                    composition_swarm::prepare_proto_info(proto.clone()),
+                );
+                self.proto_infos
+                    .insert(key, (proto.clone(), proto_info.clone()));
+                proto_info
+            }
+        }
+    }
+
+    fn combined(&mut self) -> &ProtoInfo {
+        if self.combined.is_none() {
+            let _span = tracing::info_span!("session_combine").entered();
+            let protocols = self.protocols.clone();
+            let proto_infos = protocols.iter().map(|p| self.proto_info(p)).collect();
+            let hashes: Vec<u64> = protocols.iter().map(content_hash).collect();
+            // forget components that are no longer part of the composition
+            let keys: BTreeSet<u64> = hashes.iter().cloned().collect();
+            self.proto_infos.retain(|key, _| keys.contains(key));
+            self.interface_errors
+                .retain(|(key1, key2), _| keys.contains(key1) && keys.contains(key2));
+            self.composition_steps
+                .retain(|steps, _| steps.iter().all(|(key, _)| keys.contains(key)));
+            let interface_errors = &mut self.interface_errors;
+            let check_pair = |i: usize,
+                              j: usize,
+                              proto_info1: &ProtoInfo,
+                              proto_info2: &ProtoInfo| {
+                interface_errors
+                    .entry((hashes[i], hashes[j]))
+                    .or_insert_with(|| composition_swarm::check_interface(proto_info1, proto_info2))
+                    .clone()
+            };
+            self.combined = Some(composition_swarm::combine_checked_proto_infos_with(
+                proto_infos,
+                check_pair,
+            ));
+        }
+        self.combined.as_ref().unwrap()
+    }
+
+    // The proto info with the expanded composition as its only protocol or the errors of the components.
+    fn composition(&mut self) -> Result<&ProtoInfo, ErrorReport> {
+        if !self.combined().no_errors() {
+            return Err(proto_info_to_error_report(self.combined().clone()));
+        }
+        if self.composition.is_none() {
+            let combined = self.combined().clone();
+            let hashes: Vec<u64> = self.protocols.iter().map(content_hash).collect();
+            let composition_steps = &mut self.composition_steps;
+            let compose_step = |steps: &[(usize, BTreeSet<EventType>)], compose: ComposeStep| {
+                let key = steps
+                    .iter()
+                    .map(|(k, interface)| (hashes[*k], interface.clone()))
+                    .collect();
+                composition_steps.entry(key).or_insert_with(compose).clone()
+            };
+            self.composition = Some(composition_swarm::explicit_composition_proto_info_with(
+                combined,
+                compose_step,
+            ));
+        }
+        Ok(self.composition.as_ref().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::composition_fixtures::{get_proto1, get_proto2_done};
+    use crate::composition::{
+        check_composed_swarm, compose_protocols, exact_well_formed_sub,
+        overapproximated_well_formed_sub, project_combine,
+    };
+    use itertools::Itertools;
+
+    fn get_proto3() -> SwarmProtocolType {
+        serde_json::from_str::<SwarmProtocolType>(
+            r#"{
+                "initial": "0",
+                "transitions": [
+                    { "source": "0", "target": "1", "label": { "cmd": "build", "logType": ["car", "done"], "role": "F" } },
+                    { "source": "1", "target": "2", "label": { "cmd": "test", "logType": ["report"], "role": "TR" } }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn json<T: serde::Serialize>(value: T) -> serde_json::Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    // Compare the results of a session with those of the functions recomputing everything.
+    fn assert_same_results(session: &mut CompositionSession) {
+        let protos = InterfacingProtocols(session.protocols.clone());
+        let subs = serde_json::to_string(&session.subscriptions).unwrap();
+        assert_eq!(
+            json(session.check()),
+            json(check_composed_swarm(protos.clone(), subs.clone()))
+        );
+        assert_eq!(
+            json(session.exact_well_formed_sub()),
+            json(exact_well_formed_sub(protos.clone(), subs.clone()))
+        );
+        for granularity in [
+            Granularity::Fine,
+            Granularity::Medium,
+            Granularity::Coarse,
+            Granularity::TwoStep,
+        ] {
+            assert_eq!(
+                json(session.overapproximated_well_formed_sub(granularity.clone())),
+                json(overapproximated_well_formed_sub(
+                    protos.clone(),
+                    subs.clone(),
+                    granularity
+                ))
+            );
+        }
+        assert_eq!(
+            json(session.compose_protocols()),
+            json(compose_protocols(protos.clone()))
+        );
+        assert_eq!(
+            json(session.project_combine(Role::new("T"), true)),
+            json(project_combine(protos, subs, Role::new("T"), true))
+        );
+    }
+
+    #[test]
+    fn test_session_edits() {
+        let mut session = CompositionSession::new();
+        session.set_protocols(InterfacingProtocols(vec![get_proto1(), get_proto2_done()]));
+        assert_same_results(&mut session);
+        assert_eq!(session.proto_infos.len(), 2);
+
+        assert!(matches!(
+            session.set_component(2, get_proto3()),
+            CheckResult::OK
+        ));
+        assert_same_results(&mut session);
+        assert_eq!(session.proto_infos.len(), 3);
+
+        // changing the subscription keeps the composition
+        assert!(matches!(
+            session.set_subscription(Role::new("T"), r#"["partID", "part"]"#.to_string()),
+            CheckResult::OK
+        ));
+        assert!(session.composition.is_some());
+        assert_same_results(&mut session);
+
+        assert!(matches!(session.remove_component(1), CheckResult::OK));
+        assert!(session.composition.is_none());
+        assert_same_results(&mut session);
+        assert_eq!(session.proto_infos.len(), 2);
+
+        // a component with errors
+        let mut proto = get_proto3();
+        proto.initial = crate::types::State::new("5");
+        assert!(matches!(session.set_component(0, proto), CheckResult::OK));
+        assert_same_results(&mut session);
+        assert!(matches!(session.check(), CheckResult::ERROR { .. }));
+
+        assert!(matches!(
+            session.set_component(3, get_proto1()),
+            CheckResult::ERROR { .. }
+        ));
+        assert!(matches!(
+            session.remove_component(2),
+            CheckResult::ERROR { .. }
+        ));
+        assert!(matches!(
+            session.set_subscriptions("{".to_string()),
+            CheckResult::ERROR { .. }
+        ));
+    }
+
+    #[test]
+    fn test_session_caches_components() {
+        let mut session = CompositionSession::new();
+        session.set_protocols(InterfacingProtocols(vec![get_proto1(), get_proto2_done()]));
+        session.check();
+        let key = content_hash(&get_proto2_done());
+        let before = session.proto_infos[&key].1.succeeding_events.clone();
+
+        // replacing a component by an equal one does not invalidate anything
+        assert!(matches!(
+            session.set_component(1, get_proto2_done()),
+            CheckResult::OK
+        ));
+        assert!(session.combined.is_some());
+
+        // the unchanged component is not prepared again
+        session
+            .proto_infos
+            .get_mut(&key)
+            .unwrap()
+            .1
+            .succeeding_events
+            .clear();
+        assert!(matches!(
+            session.set_component(0, get_proto3()),
+            CheckResult::OK
+        ));
+        assert!(session.combined.is_none());
+        session.combined();
+        assert!(session.proto_infos[&key].1.succeeding_events.is_empty());
+        assert!(!before.is_empty());
+        assert!(!session
+            .proto_infos
+            .contains_key(&content_hash(&get_proto1())));
+    }
+
+    #[test]
+    fn test_session_caches_interfaces() {
+        let mut session = CompositionSession::new();
+        session.set_protocols(InterfacingProtocols(vec![
+            get_proto1(),
+            get_proto2_done(),
+            get_proto3(),
+        ]));
+        session.check();
+        assert_eq!(session.interface_errors.len(), 3);
+        let keys = [get_proto2_done(), get_proto3()].map(|p| content_hash(&p));
+        let key = *session
+            .interface_errors
+            .keys()
+            .find(|(key1, key2)| keys.contains(key1) && keys.contains(key2))
+            .unwrap();
+        session
+            .interface_errors
+            .insert(key, vec![composition_swarm::Error::InvalidArg]);
+
+        // only the interfaces of the changed component are checked again
+        let mut proto = get_proto1();
+        proto.transitions[3].label.cmd = crate::types::Command::new("finish");
+        assert!(matches!(session.set_component(0, proto), CheckResult::OK));
+        session.combined();
+        assert_eq!(session.interface_errors.len(), 3);
+        assert_eq!(
+            session.interface_errors[&key],
+            vec![composition_swarm::Error::InvalidArg]
+        );
+        assert!(session
+            .interface_errors
+            .keys()
+            .all(|(key1, key2)| *key1 != content_hash(&get_proto1())
+                && *key2 != content_hash(&get_proto1())));
+    }
+    #[test]
+    fn test_session_caches_composition_steps() {
+        let mut session = CompositionSession::new();
+        session.set_protocols(InterfacingProtocols(vec![
+            get_proto1(),
+            get_proto2_done(),
+            get_proto3(),
+        ]));
+        assert_same_results(&mut session);
+        assert_eq!(session.composition_steps.len(), 2);
+        let (first_step, last_step) = session
+            .composition_steps
+            .keys()
+            .cloned()
+            .sorted_by_key(Vec::len)
+            .collect_tuple()
+            .unwrap();
+        let k = session
+            .protocols
+            .iter()
+            .position(|p| content_hash(p) == last_step[2].0)
+            .unwrap();
+
+        // only the steps composing the changed component are computed again
+        let mut proto = session.protocols[k].clone();
+        proto.transitions[0].label.cmd = crate::types::Command::new("start");
+        assert!(matches!(session.set_component(k, proto), CheckResult::OK));
+        session.combined();
+        assert_eq!(
+            session.composition_steps.keys().collect::<Vec<_>>(),
+            vec![&first_step]
+        );
+        assert_same_results(&mut session);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeSet;
+
+use petgraph::{visit::EdgeRef, Direction::Outgoing};
+
+use super::composition_machine;
+use super::composition_swarm::{
+    explicit_composition_proto_info, proto_info_to_error_report, swarms_to_proto_info,
+    synthetic_command, ErrorReport,
+};
+use super::composition_types::{
+    EventLabel, InterfacingProtocols, RoleSimulationState, SimulationDisagreement,
+    SimulationReport, SimulationSnapshot, SimulationStep,
+};
+use crate::types::{Command, EventType, MachineLabel, Role, State, StateName};
+use crate::{Graph, NodeId, Subscriptions};
+
+type Machine = petgraph::Graph<Option<State>, MachineLabel>;
+
+/*
+ * Steps the expanded composition and the projection of every role obtained from project_combine() in lockstep.
+ * Every event emitted in the composition is delivered to every projection immediately. A projection ignores
+ * events it has no input transition for in its current state(s). Projections that are not minimized may be
+ * nondeterministic, so the current states of a projection are a set.
+ */
+struct Simulation {
+    graph: Graph,
+    node: NodeId,
+    synthetic: BTreeSet<Command>,
+    machines: Vec<(Role, Machine, BTreeSet<NodeId>)>,
+}
+
+impl Simulation {
+    // The state of the composition and the projections together with the event types emitted to reach it.
+    fn snapshot(&self, events: Vec<EventType>) -> SimulationSnapshot {
+        let roles = self
+            .machines
+            .iter()
+            .map(|(role, machine, current)| RoleSimulationState {
+                role: role.clone(),
+                states: current
+                    .iter()
+                    .map(|n| machine[*n].clone().unwrap_or(State::new("")))
+                    .collect(),
+                enabled: machine_commands(machine, current),
+                expected: self.commands(role),
+            })
+            .collect();
+        SimulationSnapshot {
+            state: self.graph[self.node].state_name().clone(),
+            events,
+            roles,
+        }
+    }
+
+    // Commands enabled for role in the current state of the composition. The steps of
+    // commands emitting more than one event type are not commands of their own.
+    fn commands(&self, role: &Role) -> Vec<Command> {
+        self.graph
+            .edges_directed(self.node, Outgoing)
+            .filter(|e| e.weight().role == *role && !self.synthetic.contains(&e.weight().cmd))
+            .map(|e| e.weight().cmd.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    // Perform a step in the composition and deliver the emitted event types to the projections.
+    // Return the emitted event types or None if the step is not enabled.
+    fn step(&mut self, step: &SimulationStep) -> Option<Vec<EventType>> {
+        let first = self
+            .graph
+            .edges_directed(self.node, Outgoing)
+            .find(|e| match step {
+                SimulationStep::Command { cmd } => e.weight().cmd == *cmd,
+                SimulationStep::Event { event_type } => e.weight().get_event_type() == *event_type,
+            })?;
+        let mut edges = vec![first.id()];
+        // A command emitting more than one event type is executed completely.
+        if let SimulationStep::Command { cmd } = step {
+            let mut i = 1;
+            let mut node = first.target();
+            while let Some(next) = self
+                .graph
+                .edges_directed(node, Outgoing)
+                .find(|e| e.weight().cmd == synthetic_command(cmd, i))
+            {
+                edges.push(next.id());
+                node = next.target();
+                i += 1;
+            }
+        }
+
+        let mut events = vec![];
+        for edge in edges {
+            let event_type = self.graph[edge].get_event_type();
+            self.node = self.graph.edge_endpoints(edge).unwrap().1;
+            for (_, machine, current) in self.machines.iter_mut() {
+                let next = machine_input(machine, current, &event_type);
+                if !next.is_empty() {
+                    *current = next;
+                }
+            }
+            events.push(event_type);
+        }
+        Some(events)
+    }
+}
+
+fn machine_commands(machine: &Machine, current: &BTreeSet<NodeId>) -> Vec<Command> {
+    current
+        .iter()
+        .flat_map(|n| machine.edges_directed(*n, Outgoing))
+        .filter_map(|e| match e.weight() {
+            MachineLabel::Execute { cmd, .. } => Some(cmd.clone()),
+            MachineLabel::Input { .. } => None,
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn machine_input(
+    machine: &Machine,
+    current: &BTreeSet<NodeId>,
+    event_type: &EventType,
+) -> BTreeSet<NodeId> {
+    current
+        .iter()
+        .flat_map(|n| machine.edges_directed(*n, Outgoing))
+        .filter(|e| matches!(e.weight(), MachineLabel::Input { event_type: t } if t == event_type))
+        .map(|e| e.target())
+        .collect()
+}
+
+// Simulate the composition of protos and the projections of its roles w.r.t. subs on a sequence of steps,
+// see SimulationReport. The projections are minimized if minimize is true.
+pub fn simulate(
+    protos: InterfacingProtocols,
+    subs: &Subscriptions,
+    steps: &[SimulationStep],
+    minimize: bool,
+) -> Result<SimulationReport, ErrorReport> {
+    let _span = tracing::info_span!("simulate").entered();
+    let proto_info = swarms_to_proto_info(protos);
+    if !proto_info.no_errors() {
+        return Err(proto_info_to_error_report(proto_info));
+    }
+    let (graph, node) = match explicit_composition_proto_info(proto_info.clone()).get_ith_proto(0) {
+        Some(p) if p.initial.is_some() => (p.graph, p.initial.unwrap()),
+        _ => {
+            return Ok(SimulationReport {
+                snapshots: vec![],
+                first_disagreement: None,
+                rejected_step: None,
+            })
+        }
+    };
+    let synthetic = proto_info
+        .desugared_commands
+        .iter()
+        .flat_map(|(cmd, log_type)| (1..log_type.len()).map(|i| synthetic_command(cmd, i)))
+        .collect();
+    let machines = proto_info
+        .role_event_map
+        .keys()
+        .map(|role| {
+            let (machine, initial) =
+                composition_machine::project_combine(&proto_info, subs, role.clone(), minimize);
+            let current = initial
+                .filter(|i| machine.node_weight(*i).is_some())
+                .into_iter()
+                .collect();
+            (role.clone(), machine, current)
+        })
+        .collect();
+    let mut simulation = Simulation {
+        graph,
+        node,
+        synthetic,
+        machines,
+    };
+
+    let mut snapshots = vec![simulation.snapshot(vec![])];
+    let mut rejected_step = None;
+    for (i, step) in steps.iter().enumerate() {
+        match simulation.step(step) {
+            Some(events) => snapshots.push(simulation.snapshot(events)),
+            None => {
+                rejected_step = Some(i);
+                break;
+            }
+        }
+    }
+    let first_disagreement = snapshots.iter().enumerate().find_map(|(step, snapshot)| {
+        snapshot
+            .roles
+            .iter()
+            .find(|r| r.enabled != r.expected)
+            .map(|r| SimulationDisagreement {
+                step,
+                role: r.role.clone(),
+            })
+    });
+
+    Ok(SimulationReport {
+        snapshots,
+        first_disagreement,
+        rejected_step,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::composition_fixtures::get_interfacing_swarms_1_done;
+    use crate::composition::composition_swarm::exact_well_formed_sub;
+    use std::collections::BTreeMap;
+
+    fn command(cmd: &str) -> SimulationStep {
+        SimulationStep::Command {
+            cmd: Command::new(cmd),
+        }
+    }
+
+    #[test]
+    fn test_simulate() {
+        let protos = get_interfacing_swarms_1_done();
+        let subs = exact_well_formed_sub(protos.clone(), &BTreeMap::new()).unwrap();
+        let steps = [
+            command("request"),
+            command("get"),
+            command("deliver"),
+            command("build"),
+            SimulationStep::Event {
+                event_type: EventType::new("time"),
+            },
+        ];
+        for minimize in [false, true] {
+            let report = simulate(protos.clone(), &subs, &steps, minimize).unwrap();
+            assert_eq!(report.snapshots.len(), steps.len() + 1);
+            assert_eq!(report.first_disagreement, None);
+            assert_eq!(report.rejected_step, None);
+            // build emits both of its event types.
+            assert_eq!(
+                report.snapshots[4].events,
+                vec![EventType::new("car"), EventType::new("done")]
+            );
+            let t = |i: usize| {
+                report.snapshots[i]
+                    .roles
+                    .iter()
+                    .find(|r| r.role == Role::new("T"))
+                    .unwrap()
+                    .clone()
+            };
+            assert_eq!(t(0).enabled, vec![Command::new("request")]);
+            assert_eq!(t(1).enabled, vec![]);
+            assert_eq!(t(5).expected, vec![]);
+        }
+
+        // deliver is not enabled in the initial state.
+        let report = simulate(protos.clone(), &subs, &[command("deliver")], false).unwrap();
+        assert_eq!(report.rejected_step, Some(0));
+        assert_eq!(report.snapshots.len(), 1);
+
+        // FL does not wait for the part to be requested before getting it.
+        let mut subs = subs;
+        subs.get_mut(&Role::new("FL"))
+            .unwrap()
+            .remove(&EventType::new("partID"));
+        let report = simulate(protos, &subs, &steps, false).unwrap();
+        assert_eq!(
+            report.first_disagreement,
+            Some(SimulationDisagreement {
+                step: 0,
+                role: Role::new("FL")
+            })
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use super::composition_types::{EventLabel, Justification, ProtoInfo, SubscriptionRule};
+use crate::{
+    swarm::transition,
+    types::{EventType, Role},
+    EdgeId, Graph, Subscriptions,
+};
+use bitvec::{bitvec, vec::BitVec};
+use std::collections::{BTreeMap, BTreeSet};
+
+/*
+ * Dense representation of a subscription used inside the subscription generation fixpoints.
+ * Roles and event types are numbered and each role subscribes to a bit set of event type indices,
+ * so that adding event types and looking for involved roles does not clone or intersect ordered sets.
+ * Converted from and to Subscriptions at the boundary of the algorithms.
+ */
+#[derive(Debug, Clone)]
+pub(in crate::composition) struct DenseSubscription {
+    roles: Vec<Role>,
+    role_index: BTreeMap<Role, usize>,
+    event_types: Vec<EventType>,
+    event_index: BTreeMap<EventType, usize>,
+    subs: Vec<BitVec>,
+    // Roles that appear in the subscription, possibly subscribing to no event types.
+    present: BitVec,
+    // For each event type t, t and the event types succeeding it.
+    after: Vec<BitVec>,
+    // If explaining, the reason each (role, event type) pair was added to the subscription.
+    justifications: Option<BTreeMap<(usize, usize), Reason>>,
+}
+
+// The rule adding an event type to a subscription, together with the transition
+// and/or the event types that triggered the rule.
+#[derive(Debug, Clone)]
+pub(in crate::composition) struct Reason {
+    pub rule: SubscriptionRule,
+    pub edge: Option<EdgeId>,
+    pub event_types: BitVec,
+}
+
+// Event types that the roles subscribing to one of the event types in after must subscribe to.
+#[derive(Debug, Clone)]
+pub(in crate::composition) struct Requirement {
+    pub after: BitVec,
+    pub event_types: BitVec,
+    pub reason: Reason,
+}
+
+impl DenseSubscription {
+    // Number the roles and event types of proto_info and subscription. The roles involved after
+    // an event type t are those subscribing to t or to an event type succeeding t in succeeding_events.
+    pub fn new(
+        proto_info: &ProtoInfo,
+        succeeding_events: &BTreeMap<EventType, BTreeSet<EventType>>,
+        subscription: &Subscriptions,
+    ) -> Self {
+        let roles: Vec<Role> = proto_info
+            .role_event_map
+            .keys()
+            .chain(subscription.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let event_types: Vec<EventType> = proto_info
+            .role_event_map
+            .values()
+            .flatten()
+            .map(|label| label.get_event_type())
+            .chain(subscription.values().flatten().cloned())
+            .chain(succeeding_events.keys().cloned())
+            .chain(succeeding_events.values().flatten().cloned())
+            .chain(proto_info.branching_events.iter().flatten().cloned())
+            .chain(proto_info.interfacing_events.iter().cloned())
+            .chain(proto_info.infinitely_looping_events.iter().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let role_index: BTreeMap<Role, usize> = roles
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.clone(), i))
+            .collect();
+        let event_index: BTreeMap<EventType, usize> = event_types
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.clone(), i))
+            .collect();
+
+        let mut dense = Self {
+            subs: vec![bitvec![0; event_types.len()]; roles.len()],
+            present: bitvec![0; roles.len()],
+            after: vec![],
+            justifications: None,
+            roles,
+            role_index,
+            event_types,
+            event_index,
+        };
+        dense.after = dense
+            .event_types
+            .iter()
+            .map(|t| {
+                let succeeding = succeeding_events.get(t).into_iter().flatten();
+                dense.event_set([t].into_iter().chain(succeeding))
+            })
+            .collect();
+        let input = dense.reason(SubscriptionRule::Input, None, []);
+        for (role, event_types) in subscription {
+            let r = dense.role_index[role];
+            let event_types = dense.event_set(event_types);
+            dense.add(r, &event_types, &input);
+        }
+        dense
+    }
+
+    // Record the reason for every (role, event type) pair added from now on.
+    // Pairs already in the subscription are part of the input subscription.
+    pub fn explain(mut self) -> Self {
+        let input = self.reason(SubscriptionRule::Input, None, []);
+        let justifications = (0..self.roles.len())
+            .flat_map(|r| self.subs[r].iter_ones().map(move |t| (r, t)))
+            .map(|pair| (pair, input.clone()))
+            .collect();
+        self.justifications = Some(justifications);
+        self
+    }
+
+    pub fn reason<'a>(
+        &self,
+        rule: SubscriptionRule,
+        edge: Option<EdgeId>,
+        event_types: impl IntoIterator<Item = &'a EventType>,
+    ) -> Reason {
+        Reason {
+            rule,
+            edge,
+            event_types: self.event_set(event_types),
+        }
+    }
+
+    // The subscription and, if explaining, a justification for each of its entries.
+    // Edges of reasons are looked up in graph.
+    pub fn into_justified(mut self, graph: Option<&Graph>) -> (Subscriptions, Vec<Justification>) {
+        let justifications = self
+            .justifications
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|((r, t), reason)| Justification {
+                role: self.roles[r].clone(),
+                event_type: self.event_types[t].clone(),
+                rule: reason.rule,
+                transition: graph.zip(reason.edge).and_then(|(g, edge)| transition(g, edge)),
+                event_types: reason
+                    .event_types
+                    .iter_ones()
+                    .map(|t| self.event_types[t].clone())
+                    .collect(),
+            })
+            .collect();
+        (self.into_subscriptions(), justifications)
+    }
+
+    pub fn into_subscriptions(self) -> Subscriptions {
+        self.roles
+            .iter()
+            .enumerate()
+            .filter(|(r, _)| self.present[*r])
+            .map(|(r, role)| {
+                let event_types = self.subs[r]
+                    .iter_ones()
+                    .map(|t| self.event_types[t].clone())
+                    .collect();
+                (role.clone(), event_types)
+            })
+            .collect()
+    }
+
+    pub fn role(&self, role: &Role) -> usize {
+        self.role_index[role]
+    }
+
+    pub fn event_type(&self, event_type: &EventType) -> usize {
+        self.event_index[event_type]
+    }
+
+    pub fn event_set<'a>(&self, event_types: impl IntoIterator<Item = &'a EventType>) -> BitVec {
+        let mut set = bitvec![0; self.event_types.len()];
+        for t in event_types {
+            set.set(self.event_index[t], true);
+        }
+        set
+    }
+
+    // t and the event types succeeding t.
+    pub fn after(&self, t: usize) -> &BitVec {
+        &self.after[t]
+    }
+
+    // The union of after(t) for the event types t in event_types.
+    pub fn after_any<'a>(&self, event_types: impl IntoIterator<Item = &'a EventType>) -> BitVec {
+        let mut set = bitvec![0; self.event_types.len()];
+        for t in event_types {
+            set |= self.after[self.event_index[t]].as_bitslice();
+        }
+        set
+    }
+
+    // Add event types to the subscription of a role, return true if they were already in the subscription and false otherwise.
+    pub fn add(&mut self, role: usize, event_types: &BitVec, reason: &Reason) -> bool {
+        self.present.set(role, true);
+        if event_types.iter_ones().all(|t| self.subs[role][t]) {
+            return true;
+        }
+        if let Some(justifications) = self.justifications.as_mut() {
+            for t in event_types.iter_ones().filter(|t| !self.subs[role][*t]) {
+                justifications.insert((role, t), reason.clone());
+            }
+        }
+        self.subs[role] |= event_types.as_bitslice();
+        false
+    }
+
+    pub fn add_event_type(&mut self, role: usize, t: usize, reason: &Reason) -> bool {
+        self.present.set(role, true);
+        let unchanged = self.subs[role][t];
+        if !unchanged {
+            if let Some(justifications) = self.justifications.as_mut() {
+                justifications.insert((role, t), reason.clone());
+            }
+        }
+        self.subs[role].set(t, true);
+        unchanged
+    }
+
+    // Add event types to the subscription of every role in the subscription.
+    pub fn add_to_all(&mut self, event_types: &BitVec, reason: &Reason) {
+        for r in self.present.iter_ones().collect::<Vec<_>>() {
+            self.add(r, event_types, reason);
+        }
+    }
+
+    // The roles subscribing to one or more of the event types in event_types.
+    pub fn roles_subscribing_to(&self, event_types: &BitVec) -> Vec<usize> {
+        (0..self.roles.len())
+            .filter(|r| event_types.iter_ones().any(|t| self.subs[*r][t]))
+            .collect()
+    }
+
+    // True if there exists an event type in event_types such that all roles in roles subscribe to it.
+    pub fn all_roles_sub_to_same(&self, event_types: &BitVec, roles: &[usize]) -> bool {
+        event_types
+            .iter_ones()
+            .any(|t| roles.iter().all(|r| self.subs[*r][t]))
+    }
+
+    // Make the involved roles of a requirement subscribe to its event types. Return true if the subscription did not change.
+    pub fn apply(&mut self, requirement: &Requirement) -> bool {
+        let mut is_stable = true;
+        for r in self.roles_subscribing_to(&requirement.after) {
+            is_stable = self.add(r, &requirement.event_types, &requirement.reason) && is_stable;
+        }
+        is_stable
+    }
+
+    // Apply requirements until the subscription stabilizes.
+    pub fn fixpoint(&mut self, requirements: &[Requirement]) {
+        let mut is_stable = false;
+        while !is_stable {
+            is_stable = true;
+            for requirement in requirements {
+                is_stable = self.apply(requirement) && is_stable;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Command, SwarmLabel};
+
+    fn label(cmd: &str, event_type: &str, role: &str) -> SwarmLabel {
+        SwarmLabel {
+            cmd: Command::new(cmd),
+            log_type: vec![EventType::new(event_type)],
+            role: Role::new(role),
+        }
+    }
+
+    #[test]
+    fn test_dense_subscription() {
+        let mut proto_info = ProtoInfo::new_only_proto(vec![]);
+        proto_info.role_event_map = BTreeMap::from([
+            (Role::new("R1"), BTreeSet::from([label("c1", "a", "R1")])),
+            (Role::new("R2"), BTreeSet::from([label("c2", "b", "R2")])),
+        ]);
+        proto_info.succeeding_events =
+            BTreeMap::from([(EventType::new("a"), BTreeSet::from([EventType::new("b")]))]);
+        let subscription = BTreeMap::from([
+            (Role::new("R2"), BTreeSet::from([EventType::new("b")])),
+            (Role::new("R3"), BTreeSet::new()),
+        ]);
+        let mut dense =
+            DenseSubscription::new(&proto_info, &proto_info.succeeding_events, &subscription)
+                .explain();
+        assert_eq!(dense.clone().into_subscriptions(), subscription);
+
+        // R2 subscribes to b, which succeeds a, so it is involved after a.
+        let a = dense.event_type(&EventType::new("a"));
+        assert_eq!(dense.roles_subscribing_to(dense.after(a)), vec![dense.role(&Role::new("R2"))]);
+        let requirement = Requirement {
+            after: dense.after(a).clone(),
+            event_types: dense.event_set([&EventType::new("a")]),
+            reason: dense.reason(SubscriptionRule::Branching, None, [&EventType::new("a")]),
+        };
+        assert!(!dense.apply(&requirement));
+        assert!(dense.apply(&requirement));
+        let r1 = dense.role(&Role::new("R1"));
+        let own_emission = dense.reason(SubscriptionRule::OwnEmission, None, []);
+        assert!(!dense.add_event_type(r1, a, &own_emission));
+        assert!(dense.all_roles_sub_to_same(dense.after(a), &[r1, dense.role(&Role::new("R2"))]));
+
+        let (subscription, justifications) = dense.into_justified(None);
+        let rules: Vec<_> = justifications
+            .iter()
+            .map(|j| (j.role.clone(), j.event_type.clone(), j.rule))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                (Role::new("R1"), EventType::new("a"), SubscriptionRule::OwnEmission),
+                (Role::new("R2"), EventType::new("a"), SubscriptionRule::Branching),
+                (Role::new("R2"), EventType::new("b"), SubscriptionRule::Input),
+            ]
+        );
+        assert_eq!(
+            subscription,
+            BTreeMap::from([
+                (Role::new("R1"), BTreeSet::from([EventType::new("a")])),
+                (Role::new("R2"), BTreeSet::from([EventType::new("a"), EventType::new("b")])),
+                (Role::new("R3"), BTreeSet::new()),
+            ])
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeSet;
+
+use petgraph::{visit::EdgeRef, Direction::Outgoing};
+
+use super::composition_machine;
+use super::composition_swarm::{
+    explicit_composition_proto_info, proto_info_to_error_report, swarms_to_proto_info, ErrorReport,
+};
+use super::composition_types::{
+    EventLabel, InterfacingProtocols, ProtoInfo, RoleDisagreement, SimulatedEvent,
+    SwarmSimulationConfig, SwarmSimulationReport,
+};
+use crate::types::{Command, EventType, MachineLabel, Role, State};
+use crate::{Graph, NodeId, Subscriptions};
+
+type Machine = petgraph::Graph<Option<State>, MachineLabel>;
+
+// SplitMix64. Small and seedable, so that simulations are reproducible on every target including wasm.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number in 0..n, n must be positive.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+// An emitted event. role is the index of the emitting replica. Identified by its index in the list of events.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct Event {
+    pub event_type: EventType,
+    pub role: usize,
+    pub lamport: u64,
+}
+
+// Events are ordered by lamport timestamp, ties are broken by role and then by id.
+fn key(events: &[Event], id: usize) -> (u64, usize, usize) {
+    (events[id].lamport, events[id].role, id)
+}
+
+// Insert the event id into a log kept in the order of the events.
+pub(super) fn insert(events: &[Event], log: &mut Vec<usize>, id: usize) {
+    let position = log.partition_point(|other| key(events, *other) < key(events, id));
+    log.insert(position, id);
+}
+
+// The lamport timestamp of the next event emitted by a role with the given local log.
+pub(super) fn next_lamport(events: &[Event], log: &[usize]) -> u64 {
+    log.last().map_or(0, |id| events[*id].lamport) + 1
+}
+
+// A role with its machine and subscription.
+pub(super) struct Replica {
+    pub role: Role,
+    machine: Machine,
+    initial: Option<NodeId>,
+    pub subscription: BTreeSet<EventType>,
+}
+
+impl Replica {
+    // Run the machine over a local log. Events without a transition in the current state are ignored.
+    // Return the state reached and the ids of the consumed events.
+    pub(super) fn run(&self, events: &[Event], log: &[usize]) -> (Option<NodeId>, Vec<usize>) {
+        let mut node = self.initial;
+        let mut consumed = vec![];
+        for id in log {
+            let input = node.and_then(|n| {
+                self.machine.edges_directed(n, Outgoing).find(|e| {
+                    matches!(e.weight(), MachineLabel::Input { event_type } if *event_type == events[*id].event_type)
+                })
+            });
+            if let Some(edge) = input {
+                node = Some(edge.target());
+                consumed.push(*id);
+            }
+        }
+        (node, consumed)
+    }
+
+    // The commands enabled by the machine after running it over a local log, with the event types they emit.
+    pub(super) fn enabled(
+        &self,
+        events: &[Event],
+        log: &[usize],
+    ) -> Vec<(Command, Vec<EventType>)> {
+        self.run(events, log)
+            .0
+            .into_iter()
+            .flat_map(|n| self.machine.edges_directed(n, Outgoing))
+            .filter_map(|e| match e.weight() {
+                MachineLabel::Execute { cmd, log_type } => Some((cmd.clone(), log_type.clone())),
+                MachineLabel::Input { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/*
+ * The expanded composition together with the projection, minimized, of every role obtained from project_combine().
+ * Used to decide whether the roles agree with the composition once every event has been delivered.
+ */
+pub(super) struct Swarm {
+    graph: Graph,
+    initial: NodeId,
+    branching: BTreeSet<EventType>,
+    pub replicas: Vec<Replica>,
+}
+
+impl Swarm {
+    // None if the composition is empty.
+    pub(super) fn new(proto_info: &ProtoInfo, subs: &Subscriptions) -> Option<Self> {
+        let (graph, initial) =
+            match explicit_composition_proto_info(proto_info.clone()).get_ith_proto(0) {
+                Some(p) if p.initial.is_some() => (p.graph, p.initial.unwrap()),
+                _ => return None,
+            };
+        let replicas = proto_info
+            .role_event_map
+            .keys()
+            .map(|role| {
+                let (machine, initial) =
+                    composition_machine::project_combine(proto_info, subs, role.clone(), true);
+                Replica {
+                    role: role.clone(),
+                    initial: initial.filter(|i| machine.node_weight(*i).is_some()),
+                    machine,
+                    subscription: subs.get(role).cloned().unwrap_or_default(),
+                }
+            })
+            .collect();
+        let branching = proto_info
+            .branching_events
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        Some(Self {
+            graph,
+            initial,
+            branching,
+            replicas,
+        })
+    }
+
+    // Run the composition over all events in order, the events consumed are effective. Then compare the events
+    // consumed by each replica running over its local log with the effective events of the event types it subscribes to.
+    // Return all events in order and the first disagreement of each replica, if any.
+    pub(super) fn outcome(
+        &self,
+        events: &[Event],
+        logs: &[Vec<usize>],
+    ) -> (Vec<SimulatedEvent>, Vec<RoleDisagreement>) {
+        let mut order: Vec<usize> = (0..events.len()).collect();
+        order.sort_by_key(|id| key(events, *id));
+        let mut node = self.initial;
+        let mut effective = BTreeSet::new();
+        for id in &order {
+            let edge = self
+                .graph
+                .edges_directed(node, Outgoing)
+                .find(|e| e.weight().get_event_type() == events[*id].event_type);
+            if let Some(edge) = edge {
+                node = edge.target();
+                effective.insert(*id);
+            }
+        }
+
+        let disagreements = self
+            .replicas
+            .iter()
+            .zip(logs)
+            .filter_map(|(replica, log)| {
+                let consumed: BTreeSet<usize> = replica.run(events, log).1.into_iter().collect();
+                let event = order.iter().find(|id| {
+                    let expected = effective.contains(*id)
+                        && replica.subscription.contains(&events[**id].event_type);
+                    expected != consumed.contains(*id)
+                })?;
+                Some(RoleDisagreement {
+                    role: replica.role.clone(),
+                    event: *event,
+                    consumed: consumed.contains(event),
+                    branching: self.branching.contains(&events[*event].event_type),
+                })
+            })
+            .collect();
+
+        let log = order
+            .iter()
+            .map(|id| SimulatedEvent {
+                id: *id,
+                event_type: events[*id].event_type.clone(),
+                role: self.replicas[events[*id].role].role.clone(),
+                lamport: events[*id].lamport,
+                effective: effective.contains(id),
+            })
+            .collect();
+
+        (log, disagreements)
+    }
+}
+
+// An event on its way to a replica. Delivered in round at or later if the network is partitioned.
+struct Message {
+    event: usize,
+    to: usize,
+    at: usize,
+}
+
+struct SwarmSimulation<'a> {
+    swarm: &'a Swarm,
+    config: SwarmSimulationConfig,
+    rng: Rng,
+    events: Vec<Event>,
+    // The local log of each replica.
+    logs: Vec<Vec<usize>>,
+    pending: Vec<Message>,
+    // If partitioned, the side of the partition each replica is on.
+    partition: Option<Vec<bool>>,
+    partitions: usize,
+}
+
+impl SwarmSimulation<'_> {
+    // Let a replica execute one of the commands enabled by its machine, if any.
+    fn emit(&mut self, replica: usize, tick: usize) {
+        let enabled = self.swarm.replicas[replica].enabled(&self.events, &self.logs[replica]);
+        if enabled.is_empty() {
+            return;
+        }
+        let (_, log_type) = enabled[self.rng.below(enabled.len())].clone();
+        for event_type in log_type {
+            let id = self.events.len();
+            self.events.push(Event {
+                event_type: event_type.clone(),
+                role: replica,
+                lamport: next_lamport(&self.events, &self.logs[replica]),
+            });
+            insert(&self.events, &mut self.logs[replica], id);
+            for to in 0..self.logs.len() {
+                if to != replica && self.swarm.replicas[to].subscription.contains(&event_type) {
+                    let at = tick + 1 + self.rng.below(self.config.max_delay + 1);
+                    self.pending.push(Message { event: id, to, at });
+                }
+            }
+        }
+    }
+
+    // Deliver the messages due in round tick, or all messages if tick is None, that can cross the partition.
+    fn deliver(&mut self, tick: Option<usize>) {
+        let (deliverable, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|m| {
+                let due = tick.is_none_or(|tick| m.at <= tick);
+                let from = self.events[m.event].role;
+                let connected = self
+                    .partition
+                    .as_ref()
+                    .is_none_or(|side| side[from] == side[m.to]);
+                due && connected
+            });
+        self.pending = pending;
+        for message in deliverable {
+            insert(&self.events, &mut self.logs[message.to], message.event);
+        }
+    }
+
+    fn update_partition(&mut self) {
+        let n = self.logs.len();
+        if self.partition.is_none() && n > 1 && self.rng.chance(self.config.partition_probability) {
+            let mut side: Vec<bool> = (0..n).map(|_| self.rng.chance(0.5)).collect();
+            if side.iter().all(|s| *s == side[0]) {
+                let i = self.rng.below(n);
+                side[i] = !side[i];
+            }
+            self.partition = Some(side);
+            self.partitions += 1;
+        } else if self.partition.is_some() && self.rng.chance(self.config.heal_probability) {
+            self.partition = None;
+        }
+    }
+
+    fn simulate(&mut self) {
+        for tick in 0..self.config.ticks {
+            self.update_partition();
+            self.deliver(Some(tick));
+            let mut order: Vec<usize> = (0..self.logs.len()).collect();
+            for i in (1..order.len()).rev() {
+                order.swap(i, self.rng.below(i + 1));
+            }
+            for replica in order {
+                if self.rng.chance(self.config.emit_probability) {
+                    self.emit(replica, tick);
+                }
+            }
+        }
+        // Heal the network and wait for all events to arrive.
+        self.partition = None;
+        self.deliver(None);
+    }
+}
+
+/*
+ * Simulate the composition of protos as a swarm: every role keeps a local log, executes commands enabled by its
+ * projection (project_combine(), minimized) run over the log, and events propagate to the subscribers of their
+ * event types with random delays, partitions and merges, see SwarmSimulationConfig. The simulation is deterministic
+ * given the seed of the configuration. Once every event is delivered, the effective events are those consumed by the
+ * expanded composition when run over all events in order. A role disagrees permanently if its machine did not consume
+ * exactly the effective events of the event types it subscribes to.
+ */
+pub fn simulate_swarm(
+    protos: InterfacingProtocols,
+    subs: &Subscriptions,
+    config: SwarmSimulationConfig,
+) -> Result<SwarmSimulationReport, ErrorReport> {
+    let _span = tracing::info_span!("simulate_swarm").entered();
+    let proto_info = swarms_to_proto_info(protos);
+    if !proto_info.no_errors() {
+        return Err(proto_info_to_error_report(proto_info));
+    }
+    let Some(swarm) = Swarm::new(&proto_info, subs) else {
+        return Ok(SwarmSimulationReport {
+            log: vec![],
+            partitions: 0,
+            disagreements: vec![],
+        });
+    };
+    let mut simulation = SwarmSimulation {
+        swarm: &swarm,
+        rng: Rng(config.seed),
+        config,
+        events: vec![],
+        logs: vec![vec![]; swarm.replicas.len()],
+        pending: vec![],
+        partition: None,
+        partitions: 0,
+    };
+    simulation.simulate();
+    let (log, disagreements) = swarm.outcome(&simulation.events, &simulation.logs);
+
+    Ok(SwarmSimulationReport {
+        log,
+        partitions: simulation.partitions,
+        disagreements,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::composition_fixtures::{get_branching_proto, get_uninformed_branch_subs};
+    use crate::composition::composition_swarm::{check, exact_well_formed_sub};
+    use std::collections::BTreeMap;
+
+    fn config(seed: u64) -> SwarmSimulationConfig {
+        SwarmSimulationConfig {
+            seed,
+            ticks: 30,
+            max_delay: 3,
+            emit_probability: 0.5,
+            partition_probability: 0.2,
+            heal_probability: 0.3,
+        }
+    }
+
+    #[test]
+    fn test_simulate_swarm() {
+        let protos = InterfacingProtocols(vec![get_branching_proto()]);
+        let subs = exact_well_formed_sub(protos.clone(), &BTreeMap::new()).unwrap();
+        assert!(check(protos.clone(), &subs).is_empty());
+        for seed in 0..20 {
+            let report = simulate_swarm(protos.clone(), &subs, config(seed)).unwrap();
+            assert_eq!(report.disagreements, vec![]);
+            // Deterministic given the seed.
+            assert_eq!(
+                report,
+                simulate_swarm(protos.clone(), &subs, config(seed)).unwrap()
+            );
+        }
+
+        let subs = get_uninformed_branch_subs();
+        assert!(!check(protos.clone(), &subs).is_empty());
+        let disagreements: Vec<_> = (0..20)
+            .flat_map(|seed| {
+                simulate_swarm(protos.clone(), &subs, config(seed))
+                    .unwrap()
+                    .disagreements
+            })
+            .collect();
+        assert!(disagreements.iter().any(|d| d.branching));
+        assert!(disagreements.iter().all(|d| d.role != Role::new("R3")));
+    }
+}
@@ -4,8 +4,8 @@ use tsify::{declare, Tsify};
 
 use crate::{
     composition::composition_swarm::Error,
-    types::{Command, EventType, MachineLabel, Role, State, SwarmLabel},
-    Graph, MachineType,
+    types::{Command, ErrorInfo, EventType, MachineLabel, Role, State, SwarmLabel, Transition},
+    Graph, MachineType, Subscriptions,
 };
 
 use super::{NodeId, SwarmProtocolType};
@@ -14,6 +14,10 @@ pub type RoleEventMap = BTreeMap<Role, BTreeSet<SwarmLabel>>;
 
 pub type UnordEventPair = BTreeSet<EventType>;
 
+// Pairwise interfaces of a composition: an edge between components i < j labelled with the roles they share.
+// Any graph is allowed, including cycles such as A–B, B–C, C–A.
+pub type InterfaceGraph = BTreeMap<(usize, usize), BTreeSet<Role>>;
+
 pub fn unord_event_pair(a: EventType, b: EventType) -> UnordEventPair {
     BTreeSet::from([a, b])
 }
@@ -24,6 +28,7 @@ pub struct ProtoStruct {
     pub initial: Option<NodeId>,
     pub errors: Vec<Error>,
     pub roles: BTreeSet<Role>,
+    pub component: Option<usize>, // Index of the protocol in the input, None for the expanded composition.
 }
 
 impl ProtoStruct {
@@ -38,6 +43,7 @@ impl ProtoStruct {
             initial,
             errors,
             roles,
+            component: None,
         }
     }
 
@@ -81,9 +87,11 @@ pub struct ProtoInfo {
     pub joining_events: BTreeMap<EventType, BTreeSet<EventType>>,
     pub immediately_pre: BTreeMap<EventType, BTreeSet<EventType>>,
     pub succeeding_events: BTreeMap<EventType, BTreeSet<EventType>>,
+    pub succeeding_events_closure: BTreeMap<EventType, BTreeSet<EventType>>, // Transitive closure of succeeding_events, computed once when combining.
     pub interfacing_events: BTreeSet<EventType>,
     pub infinitely_looping_events: BTreeSet<EventType>, // Event types that do not lead to a terminal state.
     pub interface_errors: Vec<Error>,
+    pub desugared_commands: BTreeMap<Command, Vec<EventType>>, // Commands emitting more than one event type and their log types.
 }
 
 impl ProtoInfo {
@@ -98,6 +106,7 @@ impl ProtoInfo {
         interfacing_events: BTreeSet<EventType>,
         infinitely_looping_events: BTreeSet<EventType>,
         interface_errors: Vec<Error>,
+        desugared_commands: BTreeMap<Command, Vec<EventType>>,
     ) -> Self {
         Self {
             protocols,
@@ -107,9 +116,11 @@ impl ProtoInfo {
             joining_events,
             immediately_pre,
             succeeding_events,
+            succeeding_events_closure: BTreeMap::new(),
             interfacing_events,
             infinitely_looping_events,
             interface_errors,
+            desugared_commands,
         }
     }
 
@@ -122,9 +133,11 @@ impl ProtoInfo {
             joining_events: BTreeMap::new(),
             immediately_pre: BTreeMap::new(),
             succeeding_events: BTreeMap::new(),
+            succeeding_events_closure: BTreeMap::new(),
             interfacing_events: BTreeSet::new(),
             infinitely_looping_events: BTreeSet::new(),
             interface_errors: Vec::new(),
+            desugared_commands: BTreeMap::new(),
         }
     }
 
@@ -172,7 +185,7 @@ pub struct InterfacingSwarms<T>(pub Vec<CompositionComponent<T>>);
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub struct InterfacingProtocols(pub Vec<SwarmProtocolType>);
 
-#[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[tsify(into_wasm_abi, from_wasm_abi)]
 pub enum Granularity {
     Fine,
@@ -204,6 +217,350 @@ pub struct ProjectionInfo {
     pub proj_to_machine_states: ProjToMachineStates,
 }
 
+// The rule that made a role subscribe to an event type when generating a subscription.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum SubscriptionRule {
+    Input,
+    OwnEmission,
+    CausalConsistency,
+    Branching,
+    Joining,
+    Looping,
+    Interfacing,
+}
+
+// Why role subscribes to event_type: the rule that introduced the pair and the transition
+// and/or the set of event types that triggered the rule.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Justification {
+    pub role: Role,
+    pub event_type: EventType,
+    pub rule: SubscriptionRule,
+    pub transition: Option<Transition<SwarmLabel>>,
+    pub event_types: Vec<EventType>,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ExplainedSubscription {
+    pub subscription: Subscriptions,
+    pub justifications: Vec<Justification>,
+}
+
+// An overapproximating subscription compared to the exact subscription:
+// the event types it adds for each role and the number of (role, event type) pairs.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct GranularityComparison {
+    pub granularity: Granularity,
+    pub subscription: Subscriptions,
+    pub extra: Subscriptions,
+    pub size: usize,
+    pub extra_size: usize,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SubscriptionComparison {
+    pub exact: Subscriptions,
+    pub exact_size: usize,
+    pub overapproximations: Vec<GranularityComparison>,
+}
+
+// An entry of a subscription that can not be removed without breaking well-formedness
+// and the errors removing it would cause.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RequiredEntry {
+    pub role: Role,
+    pub event_type: EventType,
+    pub errors: Vec<ErrorInfo>,
+}
+
+// The entries of a well-formed subscription that can be removed together while preserving well-formedness,
+// chosen greedily, and the entries that can not be removed from the subscription without them.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct MinimalityReport {
+    pub removable: Subscriptions,
+    pub required: Vec<RequiredEntry>,
+}
+
+// An event type added to the subscription of a role when repairing a subscription,
+// the rule that added it and the errors of the input subscription it fixes.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SubscriptionAddition {
+    pub role: Role,
+    pub event_type: EventType,
+    pub rule: SubscriptionRule,
+    pub fixes: Vec<ErrorInfo>,
+}
+
+// A well-formed superset of a subscription, the entries added to it and the errors of the input subscription.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SubscriptionRepair {
+    pub subscription: Subscriptions,
+    pub added: Vec<SubscriptionAddition>,
+    pub errors: Vec<ErrorInfo>,
+}
+
+// A step of a simulation: a command executed by its role or a single event type.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum SimulationStep {
+    #[serde(rename_all = "camelCase")]
+    Command { cmd: Command },
+    #[serde(rename_all = "camelCase")]
+    Event { event_type: EventType },
+}
+
+// The current state(s) of the projection of a role, the commands enabled in them
+// and the commands enabled for the role in the current state of the composition.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RoleSimulationState {
+    pub role: Role,
+    pub states: Vec<State>,
+    pub enabled: Vec<Command>,
+    pub expected: Vec<Command>,
+}
+
+// The state of the composition and of every projection after a step, together with the event types emitted by the step.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SimulationSnapshot {
+    pub state: State,
+    pub events: Vec<EventType>,
+    pub roles: Vec<RoleSimulationState>,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SimulationDisagreement {
+    pub step: usize,
+    pub role: Role,
+}
+
+/*
+ * Result of a simulation. snapshots[0] is the initial state and snapshots[i] the state after step i.
+ * first_disagreement is the first snapshot where the commands enabled by the projection of a role
+ * differ from the commands enabled for the role by the composition.
+ * If a step is not enabled in the composition the simulation stops and rejected_step is its index.
+ */
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SimulationReport {
+    pub snapshots: Vec<SimulationSnapshot>,
+    pub first_disagreement: Option<SimulationDisagreement>,
+    pub rejected_step: Option<usize>,
+}
+
+// An event of a recorded event log: its event type, the role that emitted it and optionally its payload.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub event_type: EventType,
+    pub role: Role,
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum ConformanceViolationKind {
+    UnknownEventType,
+    WrongRole,
+    NotEnabled,
+}
+
+// The first event of a log not conforming to a composition, the state of the composition
+// before the event and the labels of the transitions enabled in that state.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ConformanceViolation {
+    pub index: usize,
+    pub event_type: EventType,
+    pub role: Role,
+    pub kind: ConformanceViolationKind,
+    pub state: State,
+    pub allowed: Vec<SwarmLabel>,
+}
+
+// Result of checking a log against a composition: the number of conforming events
+// and the state reached by them, and the first violation if any.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ConformanceReport {
+    pub conforming: usize,
+    pub state: State,
+    pub violation: Option<ConformanceViolation>,
+}
+
+/*
+ * Parameters of a simulation of a swarm with local logs. The simulation runs for ticks rounds. In each round
+ * every role executes an enabled command with probability emit_probability and events reach subscribers
+ * after a random delay of 1 to max_delay + 1 rounds unless the network is partitioned. A partition splits the
+ * roles into two groups with probability partition_probability and is healed with probability heal_probability.
+ */
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SwarmSimulationConfig {
+    pub seed: u64,
+    pub ticks: usize,
+    pub max_delay: usize,
+    pub emit_probability: f64,
+    pub partition_probability: f64,
+    pub heal_probability: f64,
+}
+
+// An event emitted during a simulation. Events are ordered by lamport timestamp, then by role and id.
+// effective is true if the event is part of the run of the composition over the ordered log.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SimulatedEvent {
+    pub id: usize,
+    pub event_type: EventType,
+    pub role: Role,
+    pub lamport: u64,
+    pub effective: bool,
+}
+
+// The first event on which the machine of a role disagrees with the composition after all events are delivered:
+// the machine consumed an event that is not effective or did not consume an effective event it subscribes to.
+// branching is true if the event type is a branching event type, i.e. the role took another branch.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct RoleDisagreement {
+    pub role: Role,
+    pub event: usize,
+    pub consumed: bool,
+    pub branching: bool,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SwarmSimulationReport {
+    pub log: Vec<SimulatedEvent>,
+    pub partitions: usize,
+    pub disagreements: Vec<RoleDisagreement>,
+}
+
+// The order in which a replica may receive the events it subscribes to. Arbitrary: any order. Fifo: the events
+// emitted by a role in the order they were emitted. Causal: an event after all events in its causal past.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum DeliveryModel {
+    Arbitrary,
+    Fifo,
+    Causal,
+}
+
+// Bounds of the model checker: at most max_events events are emitted and at most max_states states are explored.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ModelCheckConfig {
+    pub delivery: DeliveryModel,
+    pub max_events: usize,
+    pub max_states: usize,
+}
+
+// A step of a schedule: a role executing a command, emitting the events with the given ids, or an event being
+// delivered to a subscriber.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum ScheduleStep {
+    #[serde(rename_all = "camelCase")]
+    Emit {
+        role: Role,
+        cmd: Command,
+        events: Vec<usize>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Deliver { event: usize, to: Role },
+}
+
+// A shortest schedule after which every event is delivered and some role disagrees with the composition.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Counterexample {
+    pub schedule: Vec<ScheduleStep>,
+    pub log: Vec<SimulatedEvent>,
+    pub disagreements: Vec<RoleDisagreement>,
+}
+
+// complete is true if every state within max_events events was explored, it is false if exploration stopped
+// after max_states states or at a counterexample.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ModelCheckReport {
+    pub states: usize,
+    pub complete: bool,
+    pub counterexample: Option<Counterexample>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefinementViolationKind {
+    MissingInput,
+    ExtraneousTransition,
+    NonDeterministic,
+}
+
+// A transition of a machine violating refinement of a reference machine, see machine::refines().
+// trace is a shortest sequence of transitions leading to state in the machine and reference_state in the reference.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RefinementViolation {
+    pub kind: RefinementViolationKind,
+    pub trace: Vec<MachineLabel>,
+    pub state: State,
+    pub reference_state: State,
+    pub label: MachineLabel,
+}
+
+// A shortest sequence of transitions possible in exactly one of two machines, in the left one if in_left is true.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct LanguageDifference {
+    pub trace: Vec<MachineLabel>,
+    pub in_left: bool,
+}
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct LanguageComparison {
+    pub equivalent: bool,
+    pub difference: Option<LanguageDifference>,
+}
+
 /* Used when combining machines and protocols */
 pub trait EventLabel: Clone + Ord {
     fn get_event_type(&self) -> EventType;
@@ -291,21 +648,20 @@ pub trait SwarmInterface: Clone + Ord {
     fn interfacing_event_types_single<T: ProtoLabel>(&self, a: &T) -> BTreeSet<EventType>;
 }
 
-impl SwarmInterface for Role {
+// An interface consisting of a set of roles. The roles must be exactly the roles occurring in both protocols.
+// The interfacing event types are all event types emitted by the roles, these must appear in both protocols.
+impl SwarmInterface for BTreeSet<Role> {
     fn check_interface<T: ProtoLabel>(&self, a: &T, b: &T) -> Vec<Error> {
         let role_intersection: BTreeSet<Role> = a
             .get_roles()
             .intersection(&b.get_roles())
             .cloned()
             .collect();
-        println!("{:?}", role_intersection);
-        // there should only be one role that appears in both protocols
-        let mut errors =
-            if role_intersection.contains(self) && role_intersection.iter().count() == 1 {
-                vec![]
-            } else {
-                vec![Error::InvalidInterfaceRole(self.clone())]
-            };
+        // the roles appearing in both protocols should be exactly the interfacing roles
+        let mut errors: Vec<Error> = self
+            .symmetric_difference(&role_intersection)
+            .map(|role| Error::InvalidInterfaceRole(role.clone()))
+            .collect();
 
         let triples_a: BTreeSet<(Command, EventType, Role)> = a.get_labels().into_iter().collect();
         let triples_b: BTreeSet<(Command, EventType, Role)> = b.get_labels().into_iter().collect();
@@ -320,11 +676,11 @@ impl SwarmInterface for Role {
                        reference_triples: &BTreeSet<(Command, EventType, Role)>,
                        reference_event_types: &BTreeSet<EventType>,
                        reference_commands: &BTreeSet<Command>| match triple {
-            (_, et, r) if *r == *self && !reference_triples.contains(triple) => {
+            (_, et, r) if self.contains(r) && !reference_triples.contains(triple) => {
                 Some(Error::InterfaceEventNotInBothProtocols(et.clone()))
             }
             (c, et, r)
-                if *r != *self
+                if !self.contains(r)
                     && (reference_event_types.contains(et) || reference_commands.contains(c)) =>
             {
                 Some(Error::SpuriousInterface(c.clone(), et.clone(), r.clone()))
@@ -335,15 +691,13 @@ impl SwarmInterface for Role {
         errors.append(
             &mut triples_a
                 .iter()
-                .map(|triple| matcher(triple, &triples_b, &event_types_b, &commands_b))
-                .filter_map(|e| e)
+                .filter_map(|triple| matcher(triple, &triples_b, &event_types_b, &commands_b))
                 .collect(),
         );
         errors.append(
             &mut triples_b
                 .iter()
-                .map(|triple| matcher(triple, &triples_a, &event_types_a, &commands_a))
-                .filter_map(|e| e)
+                .filter_map(|triple| matcher(triple, &triples_a, &event_types_a, &commands_a))
                 .collect(),
         );
 
@@ -355,19 +709,30 @@ impl SwarmInterface for Role {
             return BTreeSet::new();
         }
 
-        a.get_labels()
-            .into_iter()
-            .filter(|(_, _, r)| *self == *r)
-            .map(|(_, e, _)| e)
-            .collect()
+        self.interfacing_event_types_single(a)
     }
 
-    // does not check anything. just returns any labels where role matches
+    // does not check anything. just returns any labels where role is in the interface
     fn interfacing_event_types_single<T: ProtoLabel>(&self, a: &T) -> BTreeSet<EventType> {
         a.get_labels()
             .into_iter()
-            .filter(|(_, _, r)| *self == *r)
+            .filter(|(_, _, r)| self.contains(r))
             .map(|(_, e, _)| e)
             .collect()
     }
-}
\ No newline at end of file
+}
+
+// A single interfacing role, i.e. the interface consisting of only this role.
+impl SwarmInterface for Role {
+    fn check_interface<T: ProtoLabel>(&self, a: &T, b: &T) -> Vec<Error> {
+        BTreeSet::from([self.clone()]).check_interface(a, b)
+    }
+
+    fn interfacing_event_types<T: ProtoLabel>(&self, a: &T, b: &T) -> BTreeSet<EventType> {
+        BTreeSet::from([self.clone()]).interfacing_event_types(a, b)
+    }
+
+    fn interfacing_event_types_single<T: ProtoLabel>(&self, a: &T) -> BTreeSet<EventType> {
+        BTreeSet::from([self.clone()]).interfacing_event_types_single(a)
+    }
+}
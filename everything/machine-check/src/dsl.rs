@@ -0,0 +1,701 @@
+/*
+ * Textual syntax for swarm protocol compositions and subscriptions.
+ *
+ *     // the warehouse protocol
+ *     component Warehouse {
+ *         initial 0
+ *         0 --[request@T<partID>]--> 1
+ *         1 --[get@FL<pos>]--> 2
+ *         2 --[deliver@T<part>]--> 0
+ *         0 --[close@D<time>]--> 3
+ *     }
+ *
+ *     subscriptions {
+ *         T: partID, part, pos, time
+ *         FL: partID, pos, time
+ *     }
+ *
+ * Each component block is a swarm protocol, the blocks form the composition in the order given.
+ * The initial state defaults to the source of the first transition. Names consist of letters,
+ * digits, '_' and '.', other names can be written in double quotes. States may also be written
+ * in parentheses, as in error messages, e.g. (0 || 1). Comments start with // and end at the
+ * end of the line.
+ */
+use crate::{
+    composition::{check_composed_swarm, composition_order, composition_types::InterfacingProtocols},
+    types::{
+        CheckResult, Command, ErrorCode, ErrorInfo, EventType, ProtocolType, Role, Span, State,
+        SwarmLabel, Transition,
+    },
+    Subscriptions,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tsify::Tsify;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spec {
+    pub protocols: InterfacingProtocols,
+    pub subscriptions: Subscriptions,
+    pub source_map: SourceMap,
+}
+
+// The result of parsing as passed to javascript.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ParsedProtocols {
+    pub protocols: InterfacingProtocols,
+    pub subscriptions: Subscriptions,
+}
+
+// Locations of the elements of the components and the subscriptions in the source text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    pub components: Vec<ComponentSpans>,
+    pub roles: BTreeMap<Role, Span>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentSpans {
+    pub header: Span,
+    pub transitions: Vec<(Transition<SwarmLabel>, Span)>,
+    pub states: BTreeMap<State, Span>,
+}
+
+// Parse a source text. Stops at the first syntax error.
+pub fn parse(src: &str) -> Result<Spec, ParseError> {
+    let _span = tracing::info_span!("parse").entered();
+    let tokens = lex(src)?;
+    Parser {
+        tokens,
+        pos: 0,
+        end: end_span(src),
+    }
+    .document()
+}
+
+// Parse a source text and check the composition for well-formedness w.r.t. the subscriptions
+// given in it. All errors are located in the source text if possible.
+pub fn check(src: &str) -> Vec<ErrorInfo> {
+    let _span = tracing::info_span!("check").entered();
+    let spec = match parse(src) {
+        Ok(spec) => spec,
+        Err(e) => return vec![e.to_info()],
+    };
+    let subs = serde_json::to_string(&spec.subscriptions).unwrap();
+    let order = composition_order(spec.protocols.clone());
+    match check_composed_swarm(spec.protocols.clone(), subs) {
+        CheckResult::OK => vec![],
+        CheckResult::ERROR { details, .. } => details
+            .into_iter()
+            .map(|info| ErrorInfo {
+                span: spec.source_map.locate(&info, &order),
+                ..info
+            })
+            .collect(),
+    }
+}
+
+impl SourceMap {
+    // Find the location in the source best describing an error: the first transition it refers to,
+    // otherwise the first state, otherwise the first transition involving one of its commands,
+    // event types or roles. order is the order in which the components are composed, as given by
+    // composition_order(): part k of a state of the expanded composition is a state of component order[k].
+    pub fn locate(&self, info: &ErrorInfo, order: &[usize]) -> Option<Span> {
+        let components: Vec<(usize, &ComponentSpans)> = match info.component {
+            Some(i) => self.components.get(i).map(|c| (i, c)).into_iter().collect(),
+            None => self.components.iter().enumerate().collect(),
+        };
+        let n = self.components.len();
+        // the part of a state of the expanded composition corresponding to component i
+        let part = |state: &State, i: usize| -> State {
+            let parts: Vec<&str> = state.split(" || ").collect();
+            match order.iter().position(|j| *j == i) {
+                Some(k) if info.component.is_none() && parts.len() == n => State::new(parts[k]),
+                _ => state.clone(),
+            }
+        };
+
+        let transition = info.transitions.iter().find_map(|t| {
+            let same_label = |i: usize, c: &ComponentSpans| {
+                c.transitions
+                    .iter()
+                    .filter(|(other, _)| other.label == t.label)
+                    .map(|(other, span)| (other.source == part(&t.source, i), *span))
+                    .collect::<Vec<_>>()
+            };
+            let candidates: Vec<(bool, Span)> = components
+                .iter()
+                .flat_map(|(i, c)| same_label(*i, c))
+                .collect();
+            candidates
+                .iter()
+                .find(|(same_source, _)| *same_source)
+                .or(candidates.first())
+                .map(|(_, span)| *span)
+        });
+        let state = || {
+            info.states.iter().find_map(|state| {
+                components
+                    .iter()
+                    .find_map(|(i, c)| c.states.get(&part(state, *i)).copied())
+            })
+        };
+        let involving = || {
+            components.iter().find_map(|(_, c)| {
+                c.transitions.iter().find_map(|(t, span)| {
+                    let involved = info.commands.contains(&t.label.cmd)
+                        || t.label.log_type.iter().any(|e| info.event_types.contains(e))
+                        || info.roles.contains(&t.label.role);
+                    involved.then_some(*span)
+                })
+            })
+        };
+        let role = || info.roles.iter().find_map(|role| self.roles.get(role).copied());
+        let header = || match info.component {
+            Some(i) => self.components.get(i).map(|c| c.header),
+            None => None,
+        };
+
+        transition
+            .or_else(state)
+            .or_else(involving)
+            .or_else(role)
+            .or_else(header)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Paren(String),
+    LBrace,
+    RBrace,
+    At,
+    Lt,
+    Gt,
+    Comma,
+    Colon,
+    ArrowStart,
+    ArrowEnd,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("'{name}'"),
+            Token::Paren(name) => format!("'({name})'"),
+            Token::LBrace => "'{'".to_string(),
+            Token::RBrace => "'}'".to_string(),
+            Token::At => "'@'".to_string(),
+            Token::Lt => "'<'".to_string(),
+            Token::Gt => "'>'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Colon => "':'".to_string(),
+            Token::ArrowStart => "'--['".to_string(),
+            Token::ArrowEnd => "']-->'".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn to_info(&self) -> ErrorInfo {
+        ErrorInfo {
+            span: Some(self.span),
+            ..ErrorInfo::new(ErrorCode::ParseError, self.message.clone())
+        }
+    }
+}
+
+fn parse_error(message: String, span: Span) -> ParseError {
+    ParseError { message, span }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+fn end_span(src: &str) -> Span {
+    let line = src.matches('\n').count() + 1;
+    let column = src.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    Span {
+        line,
+        column,
+        end_line: line,
+        end_column: column,
+    }
+}
+
+fn lex(src: &str) -> Result<Vec<(Token, Span)>, ParseError> {
+    let mut tokens = vec![];
+    let chars: Vec<char> = src.chars().collect();
+    let (mut i, mut line, mut column) = (0, 1, 1);
+    let starts_with = |i: usize, s: &str| s.chars().enumerate().all(|(k, c)| chars.get(i + k) == Some(&c));
+    while i < chars.len() {
+        let start = (line, column);
+        let c = chars[i];
+        let (token, len) = if c == '\n' {
+            i += 1;
+            line += 1;
+            column = 1;
+            continue;
+        } else if c.is_whitespace() {
+            i += 1;
+            column += 1;
+            continue;
+        } else if starts_with(i, "//") {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        } else if starts_with(i, "--[") {
+            (Token::ArrowStart, 3)
+        } else if starts_with(i, "]-->") {
+            (Token::ArrowEnd, 4)
+        } else if c == '"' || c == '(' {
+            let close = if c == '"' { '"' } else { ')' };
+            let mut content = String::new();
+            let mut len = 1;
+            loop {
+                match chars.get(i + len) {
+                    Some(c) if *c == close => break,
+                    Some('\\') if close == '"' && chars.get(i + len + 1).is_some() => {
+                        content.push(chars[i + len + 1]);
+                        len += 2;
+                    }
+                    Some('\n') | None => {
+                        let span = Span {
+                            line,
+                            column,
+                            end_line: line,
+                            end_column: column + len,
+                        };
+                        return Err(parse_error(format!("unterminated {c}"), span));
+                    }
+                    Some(c) => {
+                        content.push(*c);
+                        len += 1;
+                    }
+                }
+            }
+            let token = if c == '"' {
+                Token::Ident(content)
+            } else {
+                Token::Paren(content)
+            };
+            (token, len + 1)
+        } else if is_name_char(c) {
+            let len = chars[i..].iter().take_while(|c| is_name_char(**c)).count();
+            (Token::Ident(chars[i..i + len].iter().collect()), len)
+        } else {
+            let token = match c {
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                '@' => Token::At,
+                '<' => Token::Lt,
+                '>' => Token::Gt,
+                ',' => Token::Comma,
+                ':' => Token::Colon,
+                _ => {
+                    let span = Span {
+                        line,
+                        column,
+                        end_line: line,
+                        end_column: column + 1,
+                    };
+                    return Err(parse_error(format!("unexpected character '{c}'"), span));
+                }
+            };
+            (token, 1)
+        };
+        i += len;
+        column += len;
+        let span = Span {
+            line: start.0,
+            column: start.1,
+            end_line: line,
+            end_column: column,
+        };
+        tokens.push((token, span));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+    end: Span,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens.get(self.pos).map(|(_, s)| *s).unwrap_or(self.end)
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let found = self
+            .peek()
+            .map(|t| t.describe())
+            .unwrap_or_else(|| "end of input".to_string());
+        parse_error(format!("expected {expected}, found {found}"), self.span())
+    }
+
+    fn expect(&mut self, token: Token) -> Result<Span, ParseError> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(self.tokens[self.pos - 1].1)
+        } else {
+            Err(self.unexpected(&token.describe()))
+        }
+    }
+
+    fn name(&mut self, what: &str) -> Result<(String, Span), ParseError> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                let span = self.span();
+                self.pos += 1;
+                Ok((name, span))
+            }
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if self.peek() == Some(&Token::Ident(keyword.to_string())) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn state(&mut self) -> Result<(State, Span), ParseError> {
+        match self.peek() {
+            Some(Token::Paren(name)) => {
+                let state = State::new(name.trim());
+                let span = self.span();
+                self.pos += 1;
+                Ok((state, span))
+            }
+            _ => self
+                .name("state")
+                .map(|(name, span)| (State::new(&name), span)),
+        }
+    }
+
+    fn document(&mut self) -> Result<Spec, ParseError> {
+        let mut protocols = vec![];
+        let mut subscriptions = Subscriptions::new();
+        let mut source_map = SourceMap::default();
+        while self.peek().is_some() {
+            let span = self.span();
+            if self.keyword("component") {
+                let (proto, spans) = self.component(span)?;
+                protocols.push(proto);
+                source_map.components.push(spans);
+            } else if self.keyword("subscriptions") {
+                self.subscriptions(&mut subscriptions, &mut source_map.roles)?;
+            } else {
+                return Err(self.unexpected("'component' or 'subscriptions'"));
+            }
+        }
+        Ok(Spec {
+            protocols: InterfacingProtocols(protocols),
+            subscriptions,
+            source_map,
+        })
+    }
+
+    fn component(
+        &mut self,
+        keyword: Span,
+    ) -> Result<(ProtocolType<SwarmLabel>, ComponentSpans), ParseError> {
+        let header = match self.peek() {
+            Some(Token::Ident(_)) => keyword.to(self.name("component name")?.1),
+            _ => keyword,
+        };
+        self.expect(Token::LBrace)?;
+        let mut initial = None;
+        let mut spans = ComponentSpans {
+            header,
+            transitions: vec![],
+            states: BTreeMap::new(),
+        };
+        loop {
+            if self.peek() == Some(&Token::RBrace) {
+                self.pos += 1;
+                break;
+            }
+            let span = self.span();
+            if self.keyword("initial") {
+                if initial.is_some() {
+                    return Err(parse_error("initial state given twice".to_string(), span));
+                }
+                let (state, state_span) = self.state()?;
+                spans.states.entry(state.clone()).or_insert(state_span);
+                initial = Some(state);
+                continue;
+            }
+            let (source, source_span) = self.state()?;
+            self.expect(Token::ArrowStart)?;
+            let label = self.label()?;
+            self.expect(Token::ArrowEnd)?;
+            let (target, target_span) = self.state()?;
+            spans.states.entry(source.clone()).or_insert(source_span);
+            spans.states.entry(target.clone()).or_insert(target_span);
+            let transition = Transition {
+                label,
+                source,
+                target,
+            };
+            spans
+                .transitions
+                .push((transition, source_span.to(target_span)));
+        }
+        let initial = match (initial, spans.transitions.first()) {
+            (Some(initial), _) => initial,
+            (None, Some((t, _))) => t.source.clone(),
+            (None, None) => {
+                return Err(parse_error(
+                    "component without transitions must give an initial state".to_string(),
+                    header,
+                ))
+            }
+        };
+        let proto = ProtocolType {
+            initial,
+            transitions: spans.transitions.iter().map(|(t, _)| t.clone()).collect(),
+        };
+        Ok((proto, spans))
+    }
+
+    fn label(&mut self) -> Result<SwarmLabel, ParseError> {
+        let (cmd, _) = self.name("command")?;
+        self.expect(Token::At)?;
+        let (role, _) = self.name("role")?;
+        self.expect(Token::Lt)?;
+        let mut log_type = vec![];
+        if self.peek() != Some(&Token::Gt) {
+            log_type.push(EventType::new(&self.name("event type")?.0));
+            while self.peek() == Some(&Token::Comma) {
+                self.pos += 1;
+                log_type.push(EventType::new(&self.name("event type")?.0));
+            }
+        }
+        self.expect(Token::Gt)?;
+        Ok(SwarmLabel {
+            cmd: Command::new(&cmd),
+            log_type,
+            role: Role::new(&role),
+        })
+    }
+
+    fn subscriptions(
+        &mut self,
+        subscriptions: &mut Subscriptions,
+        roles: &mut BTreeMap<Role, Span>,
+    ) -> Result<(), ParseError> {
+        self.expect(Token::LBrace)?;
+        while self.peek() != Some(&Token::RBrace) {
+            let (role, span) = self.name("role or '}'")?;
+            let role = Role::new(&role);
+            self.expect(Token::Colon)?;
+            let sub = subscriptions.entry(role.clone()).or_default();
+            roles.entry(role).or_insert(span);
+            // the event types of a role are separated by commas, the next role starts without one
+            if matches!(self.peek(), Some(Token::Ident(_)))
+                && self.tokens.get(self.pos + 1).map(|(t, _)| t) != Some(&Token::Colon)
+            {
+                sub.insert(EventType::new(&self.name("event type")?.0));
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    sub.insert(EventType::new(&self.name("event type")?.0));
+                }
+            }
+        }
+        self.expect(Token::RBrace)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SwarmProtocolType;
+    use std::collections::BTreeSet;
+
+    fn get_source() -> &'static str {
+        r#"// Example from coplaws slides
+component Warehouse {
+    initial 0
+    0 --[request@T<partID>]--> 1
+    1 --[get@FL<pos>]--> 2
+    2 --[deliver@T<part>]--> 0
+    0 --[close@D<time>]--> 3
+}
+
+component "Factory" {
+    (0) --[request@T<partID>]--> (1)
+    (1) --[deliver@T<part>]--> (2)
+    (2) --[build@F<car>]--> (3)
+}
+
+subscriptions {
+    T: partID, part
+    F:
+    D: partID, part, time
+}
+"#
+    }
+
+    #[test]
+    fn test_parse() {
+        let spec = parse(get_source()).unwrap();
+        let warehouse = serde_json::from_str::<SwarmProtocolType>(
+            r#"{
+                "initial": "0",
+                "transitions": [
+                    { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                    { "source": "1", "target": "2", "label": { "cmd": "get", "logType": ["pos"], "role": "FL" } },
+                    { "source": "2", "target": "0", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+                    { "source": "0", "target": "3", "label": { "cmd": "close", "logType": ["time"], "role": "D" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(spec.protocols.0.len(), 2);
+        assert_eq!(spec.protocols.0[0], warehouse);
+        assert_eq!(spec.protocols.0[1].initial, State::new("0"));
+        assert_eq!(
+            spec.subscriptions,
+            BTreeMap::from([
+                (Role::new("T"), BTreeSet::from([EventType::new("partID"), EventType::new("part")])),
+                (Role::new("F"), BTreeSet::new()),
+                (
+                    Role::new("D"),
+                    BTreeSet::from([EventType::new("partID"), EventType::new("part"), EventType::new("time")])
+                ),
+            ])
+        );
+        assert_eq!(
+            spec.source_map.components[0].transitions[1].1,
+            Span {
+                line: 5,
+                column: 5,
+                end_line: 5,
+                end_column: 27
+            }
+        );
+        assert_eq!(
+            spec.source_map.components[1].header,
+            Span {
+                line: 10,
+                column: 1,
+                end_line: 10,
+                end_column: 20
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |src: &str| {
+            let e = parse(src).unwrap_err().to_info();
+            (e.code, e.message, e.span.map(|s| (s.line, s.column)))
+        };
+        assert_eq!(
+            error("component {\n    0 --[request@T<partID]--> 1\n}"),
+            (
+                ErrorCode::ParseError,
+                "expected '>', found ']-->'".to_string(),
+                Some((2, 26))
+            )
+        );
+        assert_eq!(
+            error("component {\n    0 --[request@T<partID>]--> 1\n"),
+            (
+                ErrorCode::ParseError,
+                "expected state, found end of input".to_string(),
+                Some((3, 1))
+            )
+        );
+        assert_eq!(
+            error("component {\n  0 -> 1 }"),
+            (
+                ErrorCode::ParseError,
+                "unexpected character '-'".to_string(),
+                Some((2, 5))
+            )
+        );
+        assert_eq!(
+            error("component A {}"),
+            (
+                ErrorCode::ParseError,
+                "component without transitions must give an initial state".to_string(),
+                Some((1, 1))
+            )
+        );
+        assert_eq!(
+            error("protocol {}"),
+            (
+                ErrorCode::ParseError,
+                "expected 'component' or 'subscriptions', found 'protocol'".to_string(),
+                Some((1, 1))
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_locations() {
+        let located = |src: &str| -> Vec<(ErrorCode, Option<(usize, usize)>)> {
+            check(src)
+                .into_iter()
+                .map(|e| (e.code, e.span.map(|s| (s.line, s.column))))
+                .collect()
+        };
+        assert!(check(&get_source().replace("T: partID, part", "T: partID, part, pos, time, car")
+            .replace("F:", "F: partID, part, time, car")
+            .replace("D: partID, part, time", "D: partID, part, time\n    FL: partID, pos, time"))
+            .is_empty());
+
+        // FL is missing from the subscriptions entirely: branching error on the request transitions in the composition
+        let errors = located(get_source());
+        assert!(!errors.is_empty());
+        assert!(errors
+            .iter()
+            .all(|(_, span)| span.is_some()));
+        assert!(errors.contains(&(ErrorCode::RoleNotSubscribedToBranch, Some((4, 5)))));
+
+        // errors in a component are located in that component
+        let src = "component {\n    0 --[a@R<e>]--> 1\n}\ncomponent {\n    0 --[b@S<f>]--> 1\n    0 --[b@S<g>]--> 2\n}";
+        assert_eq!(
+            located(src),
+            vec![(ErrorCode::CommandOnMultipleTransitions, Some((5, 5)))]
+        );
+    }
+
+    #[test]
+    fn test_locate_out_of_canonical_order() {
+        // the second component comes first in the canonical order, so its states come first in the composition
+        let src = "component {\n    initial x\n    x --[a@R<e>]--> y\n}\ncomponent {\n    initial b\n    b --[c@S<g>]--> d\n}";
+        let spec = parse(src).unwrap();
+        let order = composition_order(spec.protocols.clone());
+        assert_eq!(order, vec![1, 0]);
+        let mut info = ErrorInfo::new(ErrorCode::Other, String::new());
+        info.add_state(&State::new("d || y"));
+        let span = spec.source_map.locate(&info, &order);
+        assert!(span.is_some());
+        assert_eq!(span, spec.source_map.components[0].states.get(&State::new("y")).copied());
+    }
+}
@@ -1,8 +1,25 @@
-import { check_swarm, check_projection, check_composed_swarm, exact_well_formed_sub, overapproximated_well_formed_sub, check_composed_projection,
-  revised_projection, project_combine, compose_protocols, projection_information,
+import { check_swarm, check_projection, check_refinement, check_composed_swarm, check_composed_swarm_on_the_fly, exact_well_formed_sub, overapproximated_well_formed_sub,
+  exact_well_formed_sub_explained, overapproximated_well_formed_sub_explained, compare_well_formed_subs, check_subscription_minimality, repair_subscription, simulate, check_event_log, simulate_swarm, model_check,
+  check_composed_projection, check_composed_refinement, determinize_machine, minimize_machine, compare_machine_languages,
+  revised_projection, project_combine, compose_protocols, composition_order, projection_information, render_swarm, render_machine,
+  parse_protocols, check_protocol_source, ParsedProtocols, CompositionSession as CompositionSessionInner,
   CheckResult, MachineType, SwarmProtocolType, Subscriptions, InterfacingSwarms as InterfacingSwarmsInner, CompositionComponent as CompositionComponentInner, Role, DataResult, Granularity,
-  ProjectionInfo, InterfacingProtocols } from '../pkg/machine_check.js'
-export { MachineType, SwarmProtocolType, Subscriptions, Role, CheckResult as Result, DataResult, Granularity, ProjectionInfo, InterfacingProtocols }
+  ProjectionInfo, InterfacingProtocols, ErrorInfo, ErrorCode, Severity, WitnessInfo, RenderFormat,
+  ExplainedSubscription, Justification, SubscriptionRule, SubscriptionComparison, GranularityComparison,
+  MinimalityReport, RequiredEntry, SubscriptionRepair, SubscriptionAddition,
+  SimulationStep, SimulationReport, SimulationSnapshot, RoleSimulationState, SimulationDisagreement,
+  ConformanceReport, ConformanceViolation, ConformanceViolationKind,
+  SwarmSimulationConfig, SwarmSimulationReport, SimulatedEvent, RoleDisagreement,
+  DeliveryModel, ModelCheckConfig, ModelCheckReport, Counterexample, ScheduleStep,
+  LanguageComparison, LanguageDifference } from '../pkg/machine_check.js'
+export { MachineType, SwarmProtocolType, Subscriptions, Role, CheckResult as Result, DataResult, Granularity, ProjectionInfo, InterfacingProtocols,
+  ErrorInfo, ErrorCode, Severity, WitnessInfo, RenderFormat, ParsedProtocols, ExplainedSubscription, Justification, SubscriptionRule,
+  SubscriptionComparison, GranularityComparison, MinimalityReport, RequiredEntry,
+  SubscriptionRepair, SubscriptionAddition, SimulationStep, SimulationReport, SimulationSnapshot, RoleSimulationState,
+  SimulationDisagreement, ConformanceReport, ConformanceViolation, ConformanceViolationKind,
+  SwarmSimulationConfig, SwarmSimulationReport, SimulatedEvent, RoleDisagreement,
+  DeliveryModel, ModelCheckConfig, ModelCheckReport, Counterexample, ScheduleStep,
+  LanguageComparison, LanguageDifference }
 export type CompositionComponent = CompositionComponentInner<Role>;
 export type InterfacingSwarms = InterfacingSwarmsInner<Role>;
 
@@ -39,11 +56,34 @@ export function checkProjection(
   return check_projection(swarm, JSON.stringify(subscriptions), role, machine)
 }
 
+/**
+ * Check that a machine implements a subset of the commands of some role of a swarm protocol.
+ * Unlike ```checkProjection```, the machine may leave out commands of the projection, but it must react to
+ * every event the projection reacts to and may not offer commands the projection does not offer. Every violation
+ * is reported with a shortest trace of transitions leading to it.
+ *
+ * @param swarm - A swarm protocol.
+ * @param subscriptions - A subscription.
+ * @param role - The role to check against.
+ * @param machine - The machine to check.
+ * @returns - Result indicating successful verification or a list of errors.
+ */
+export function checkRefinement(
+  swarm: SwarmProtocolType,
+  subscriptions: Subscriptions,
+  role: string,
+  machine: MachineType,
+): CheckResult {
+  return check_refinement(swarm, JSON.stringify(subscriptions), role, machine)
+}
+
 /**
  * Check that a composed swarm protocol is *well-formed* w.r.t. a subscription.
  * The composition is given implicitly as an array of the swarm protocols that
  * form the composition. A single swarm protocol can be checked for well-formedness
  * by passing an array containing just that single swarm protocol.
+ * States of the composition in error messages are named ```s0 || s1 || ...```,
+ * see ```compositionOrder``` for the protocol each part belongs to.
  *
  * @param protos - An array of swarm protocols representing a composition.
  * @param subscriptions - A subscription.
@@ -53,6 +93,23 @@ export function checkComposedSwarmProtocol(protos: InterfacingProtocols, subscri
   return check_composed_swarm(protos, JSON.stringify(subscriptions))
 }
 
+/**
+ * Check that a composed swarm protocol is *well-formed* w.r.t. a subscription
+ * like ```checkComposedSwarmProtocol```, but explore the composition lazily and
+ * skip redundant interleavings of concurrent event types. Each violation is reported
+ * once per event type and role.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @param stopAtFirst - Stop exploring at the first violation and report only that one. Only violations of
+ * causal consistency stop the exploration early: determinacy violations are found after exploring the
+ * whole composition and are only reported if there are no others.
+ * @returns - Result indicating successful verification or a list of error messages.
+ */
+export function checkComposedSwarmProtocolOnTheFly(protos: InterfacingProtocols, subscriptions: Subscriptions, stopAtFirst: boolean = false): CheckResult {
+  return check_composed_swarm_on_the_fly(protos, JSON.stringify(subscriptions), stopAtFirst)
+}
+
 /**
  * Generate the smallest subscription that is well-formed w.r.t. to
  * a swarm protocol composition and contains an input subscription.
@@ -79,6 +136,73 @@ export function overapproxWFSubscriptions(protos: InterfacingProtocols, subscrip
   return overapproximated_well_formed_sub(protos, JSON.stringify(subscriptions), granularity);
 }
 
+/**
+ * Generate the smallest well-formed subscription like ```exactWFSubscriptions``` and
+ * justify each of its entries: for every role and event type the rule that introduced it
+ * (input, own emission, causal consistency, branching, joining, looping or interfacing)
+ * and the transition and/or event types that triggered the rule.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @returns - Result containing the computed subscription and its justifications or a list of error messages.
+ */
+export function exactWFSubscriptionsExplained(protos: InterfacingProtocols, subscriptions: Subscriptions): DataResult<ExplainedSubscription> {
+  return exact_well_formed_sub_explained(protos, JSON.stringify(subscriptions));
+}
+
+/**
+ * Generate an overapproximating well-formed subscription like ```overapproxWFSubscriptions```
+ * and justify each of its entries, see ```exactWFSubscriptionsExplained```.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @param granularity - The precision of the approximation.
+ * @returns - Result containing the computed subscription and its justifications or a list of error messages.
+ */
+export function overapproxWFSubscriptionsExplained(protos: InterfacingProtocols, subscriptions: Subscriptions, granularity: Granularity): DataResult<ExplainedSubscription> {
+  return overapproximated_well_formed_sub_explained(protos, JSON.stringify(subscriptions), granularity);
+}
+
+/**
+ * Compute the smallest well-formed subscription and the overapproximation of every granularity,
+ * and report for each granularity the event types it adds per role and the size of the subscriptions.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @returns - Result containing the comparison or a list of error messages.
+ */
+export function compareWFSubscriptions(protos: InterfacingProtocols, subscriptions: Subscriptions): DataResult<SubscriptionComparison> {
+  return compare_well_formed_subs(protos, JSON.stringify(subscriptions));
+}
+
+/**
+ * Check which entries of a well-formed subscription can be removed while preserving well-formedness.
+ * Entries are removed greedily one after the other as long as the subscription stays well-formed,
+ * so all removable entries can be removed together.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A well-formed subscription.
+ * @returns - Result containing the removable entries and, for every other entry, the errors removing it from
+ * the subscription without the removable entries would cause, or the errors of the subscription if it is not well-formed.
+ */
+export function checkSubscriptionMinimality(protos: InterfacingProtocols, subscriptions: Subscriptions): DataResult<MinimalityReport> {
+  return check_subscription_minimality(protos, JSON.stringify(subscriptions));
+}
+
+/**
+ * Repair a subscription for which ```checkComposedSwarmProtocol``` fails: extend it to the smallest
+ * well-formed subscription containing it and report which event types were added to which roles,
+ * the rule adding each of them and the errors each addition fixes.
+ * A well-formed subscription is returned unchanged.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @returns - Result containing the repaired subscription and the additions or a list of error messages.
+ */
+export function repairSubscription(protos: InterfacingProtocols, subscriptions: Subscriptions): DataResult<SubscriptionRepair> {
+  return repair_subscription(protos, JSON.stringify(subscriptions));
+}
+
 /**
  * Check that a machine correctly implements some role of a (possibly composed) swarm protocol.
  *
@@ -97,6 +221,60 @@ export function checkComposedProjection(
   return check_composed_projection(protos, JSON.stringify(subscriptions), role, machine)
 }
 
+/**
+ * Check that a machine implements a subset of the commands of some role of a (possibly composed) swarm protocol.
+ * Unlike ```checkComposedProjection```, the machine may leave out commands of the projection, but it must react to
+ * every event the projection reacts to and may not offer commands the projection does not offer. Every violation
+ * is reported with a shortest trace of transitions leading to it.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @param role - The role (given as a string).
+ * @param machine - The machine to check.
+ * @returns - Result indicating successful verification or a list of error messages.
+ */
+export function checkComposedRefinement(
+  protos: InterfacingProtocols,
+  subscriptions: Subscriptions,
+  role: Role,
+  machine: MachineType,
+): CheckResult {
+  return check_composed_refinement(protos, JSON.stringify(subscriptions), role, machine)
+}
+
+/**
+ * Determinize a machine using the subset construction. States of the result are named after the sets of states
+ * of the machine they represent.
+ *
+ * @param machine - The machine to determinize.
+ * @returns - Result containing the deterministic machine or a list of error messages.
+ */
+export function determinizeMachine(machine: MachineType): DataResult<MachineType> {
+  return determinize_machine(machine)
+}
+
+/**
+ * Determinize and minimize a machine. The result accepts the same sequences of transitions as the machine.
+ *
+ * @param machine - The machine to minimize.
+ * @returns - Result containing the minimal machine or a list of error messages.
+ */
+export function minimizeMachine(machine: MachineType): DataResult<MachineType> {
+  return minimize_machine(machine)
+}
+
+/**
+ * Decide whether two machines accept the same sequences of transitions, independently of any protocol.
+ *
+ * @param left - A machine.
+ * @param right - Another machine.
+ * @returns - Result telling whether the machines are equivalent and otherwise containing a shortest sequence of
+ * transitions possible in only one of them, or a list of error messages.
+ */
+export function compareMachineLanguages(left: MachineType, right: MachineType): DataResult<LanguageComparison> {
+  return compare_machine_languages(left, right)
+}
+
 /**
  * Compute the projection of a swarm protocol over a role w.r.t. a subscription.
  *
@@ -129,8 +307,75 @@ export function projectCombineMachines(protos: InterfacingProtocols, subscriptio
   return project_combine(protos, JSON.stringify(subscriptions), role, minimize)
 }
 
+/**
+ * Step the expanded composition and the projection of every role (see ```projectCombineMachines```) in lockstep,
+ * delivering every emitted event to every projection immediately. After each step the report contains the state of
+ * the composition, the state(s) of every projection and the commands enabled per role, and it points out the first
+ * step where the commands enabled by the projection of a role differ from those enabled by the composition.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @param steps - The commands to execute or event types to emit, in order.
+ * @param minimize - The projections are minimized if ```minimize``` is true and used as is otherwise.
+ * @returns - Result containing the simulation report or a list of error messages.
+ */
+export function simulateComposition(protos: InterfacingProtocols, subscriptions: Subscriptions, steps: SimulationStep[], minimize: boolean = false): DataResult<SimulationReport> {
+  return simulate(protos, JSON.stringify(subscriptions), JSON.stringify(steps), minimize)
+}
+
+/**
+ * Check a recorded event log against a swarm protocol composition. Every event must be emitted by the role
+ * emitting its event type in the protocols and be enabled in the expanded composition after the events before it.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param log - The log as JSON lines, one ```{"eventType": ..., "role": ..., "payload": ...}``` object per line.
+ * @returns - Result containing the number of conforming events, the state they lead to and the first violation
+ * with the allowed events at that point, or a list of error messages.
+ */
+export function checkEventLog(protos: InterfacingProtocols, log: string): DataResult<ConformanceReport> {
+  return check_event_log(protos, log)
+}
+
+/**
+ * Simulate a swarm protocol composition as an eventually consistent swarm. Every role keeps a local log and
+ * executes commands enabled by its projection (see ```projectCombineMachines```), events reach the subscribers of
+ * their event types with random delays and the roles may be partitioned for a while. Once all events are delivered,
+ * the report lists the events ordered by lamport timestamp, whether they are part of the run of the composition, and
+ * the first event on which the machine of each role disagrees with the composition. The simulation is deterministic
+ * given ```config.seed```.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @param config - The seed, the number of rounds, the maximal delay of an event in rounds, the probability of a role
+ * emitting an event in a round and the probabilities of a partition occurring and healing in a round.
+ * @returns - Result containing the simulation report or a list of error messages.
+ */
+export function simulateSwarm(protos: InterfacingProtocols, subscriptions: Subscriptions, config: SwarmSimulationConfig): DataResult<SwarmSimulationReport> {
+  return simulate_swarm(protos, JSON.stringify(subscriptions), config)
+}
+
+/**
+ * Explore every interleaving of emissions and deliveries of a swarm running a composition, up to a bound on the
+ * number of events and explored states. Roles execute the commands enabled by their projections and receive the
+ * events they subscribe to in any order allowed by the delivery model. Whenever all events are delivered, every role
+ * must agree with the composition (see ```simulateSwarm```). Otherwise a shortest schedule leading to a
+ * disagreement is returned.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @param subscriptions - A subscription.
+ * @param config - The delivery model (arbitrary, FIFO per emitting role or causal), the maximal number of events
+ * and the maximal number of states to explore.
+ * @returns - Result containing the number of explored states, whether the exploration was complete and a
+ * counterexample if one was found, or a list of error messages. Exploration stops at the first counterexample,
+ * so it is only complete if no counterexample was found.
+ */
+export function modelCheck(protos: InterfacingProtocols, subscriptions: Subscriptions, config: ModelCheckConfig): DataResult<ModelCheckReport> {
+  return model_check(protos, JSON.stringify(subscriptions), config)
+}
+
 /**
  * Construct the composition of a number of swarm protocols.
+ * Its states are named ```s0 || s1 || ...```, see ```compositionOrder```.
  *
  * @param protos - An array of swarm protocols representing a composition.
  * @returns - Result containing the expanded composition or a list of error messages.
@@ -139,6 +384,19 @@ export function composeProtocols(protos: InterfacingProtocols): DataResult<Swarm
   return compose_protocols(protos)
 }
 
+/**
+ * The order in which protocols are composed. Part k of the name of a state of the composition
+ * is a state of ```protos[compositionOrder(protos)[k]]```. The order is determined by the
+ * initial states and transitions of the protocols, so reordering ```protos``` does not change
+ * the names of the states.
+ *
+ * @param protos - An array of swarm protocols representing a composition.
+ * @returns - For each part of a state name, the index of its protocol in ```protos```.
+ */
+export function compositionOrder(protos: InterfacingProtocols): number[] {
+  return Array.from(composition_order(protos))
+}
+
 /**
  * Returns a projection of a composed swarm protocol over a role w.r.t. a subscription
  * and information used for running a branch-tracking adapted machine implementing some role.
@@ -155,4 +413,154 @@ export function composeProtocols(protos: InterfacingProtocols): DataResult<Swarm
  */
 export function projectionInformation(role: Role, protos: InterfacingProtocols, k: number, subscriptions: Subscriptions, machine: MachineType, minimize: boolean): DataResult<ProjectionInfo> {
   return projection_information(role, protos, k, JSON.stringify(subscriptions), machine, minimize);
-}
\ No newline at end of file
+}
+/**
+ * Render a swarm protocol, e.g. a composition returned by ```composeProtocols```, as a
+ * Graphviz DOT or Mermaid state diagram.
+ *
+ * @param proto - A swarm protocol.
+ * @param format - ```'Dot'``` or ```'Mermaid'```.
+ * @param errors - Errors of a failed check. The transitions they refer to are highlighted.
+ * @returns - Result containing the diagram or a list of error messages.
+ */
+export function renderSwarmProtocol(proto: SwarmProtocolType, format: RenderFormat, errors: ErrorInfo[] = []): DataResult<string> {
+  return render_swarm(proto, format, JSON.stringify(errors.flatMap((e) => e.transitions)))
+}
+
+/**
+ * Render a machine, e.g. a projection, as a Graphviz DOT or Mermaid state diagram.
+ *
+ * @param machine - A machine.
+ * @param format - ```'Dot'``` or ```'Mermaid'```.
+ * @param highlight - Transitions of the machine to highlight.
+ * @returns - Result containing the diagram or a list of error messages.
+ */
+export function renderMachine(machine: MachineType, format: RenderFormat, highlight: MachineType['transitions'] = []): DataResult<string> {
+  return render_machine(machine, format, JSON.stringify(highlight))
+}
+
+/**
+ * Parse a composition and a subscription written in the textual protocol syntax, e.g.
+ * ```
+ * component Warehouse {
+ *     initial 0
+ *     0 --[request@T<partID>]--> 1
+ *     1 --[deliver@T<part>]--> 2
+ * }
+ * subscriptions {
+ *     T: partID, part
+ * }
+ * ```
+ *
+ * @param src - The source text.
+ * @returns - Result containing the protocols and the subscription or the syntax error with its location.
+ */
+export function parseProtocols(src: string): DataResult<ParsedProtocols> {
+  return parse_protocols(src)
+}
+
+/**
+ * Check that the composition written in the textual protocol syntax is *well-formed* w.r.t.
+ * the subscription given in the source. The errors carry their location in the source.
+ *
+ * @param src - The source text.
+ * @returns - Result indicating successful verification or a list of errors.
+ */
+export function checkProtocolSource(src: string): CheckResult {
+  return check_protocol_source(src)
+}
+
+/**
+ * A composition that is edited and checked repeatedly, e.g. by an editor on every keystroke.
+ * Each component is prepared once and cached by its content, so changing a single component
+ * only prepares that component again and changing the subscription only reruns the checks.
+ * Call ```free``` when the session is no longer needed.
+ */
+export class CompositionSession {
+  private inner = new CompositionSessionInner()
+
+  /**
+   * @param protos - An array of swarm protocols replacing all components of the composition.
+   */
+  setProtocols(protos: InterfacingProtocols): void {
+    this.inner.set_protocols(protos)
+  }
+
+  /**
+   * @param k - The index of the component to replace. The component is added if ```k``` is the number of components.
+   * @param proto - A swarm protocol.
+   * @returns - Result indicating success or an invalid index.
+   */
+  setComponent(k: number, proto: SwarmProtocolType): CheckResult {
+    return this.inner.set_component(k, proto)
+  }
+
+  /**
+   * @param k - The index of the component to remove.
+   * @returns - Result indicating success or an invalid index.
+   */
+  removeComponent(k: number): CheckResult {
+    return this.inner.remove_component(k)
+  }
+
+  /**
+   * @param subscriptions - A subscription replacing the current one.
+   * @returns - Result indicating success or a list of error messages.
+   */
+  setSubscriptions(subscriptions: Subscriptions): CheckResult {
+    return this.inner.set_subscriptions(JSON.stringify(subscriptions))
+  }
+
+  /**
+   * @param role - A role (given as a string).
+   * @param eventTypes - The event types the role subscribes to.
+   * @returns - Result indicating success or a list of error messages.
+   */
+  setSubscription(role: Role, eventTypes: string[]): CheckResult {
+    return this.inner.set_subscription(role, JSON.stringify(eventTypes))
+  }
+
+  /**
+   * Check that the composition is *well-formed* w.r.t. the subscription, see ```checkComposedSwarmProtocol```.
+   */
+  check(): CheckResult {
+    return this.inner.check()
+  }
+
+  /**
+   * Generate the smallest well-formed subscription containing the subscription, see ```exactWFSubscriptions```.
+   */
+  exactWFSubscriptions(): DataResult<Subscriptions> {
+    return this.inner.exact_well_formed_sub()
+  }
+
+  /**
+   * Generate a well-formed subscription containing the subscription compositionally, see ```overapproxWFSubscriptions```.
+   *
+   * @param granularity - The precision of the approximation.
+   */
+  overapproxWFSubscriptions(granularity: Granularity): DataResult<Subscriptions> {
+    return this.inner.overapproximated_well_formed_sub(granularity)
+  }
+
+  /**
+   * Construct the composition, see ```composeProtocols```.
+   */
+  composeProtocols(): DataResult<SwarmProtocolType> {
+    return this.inner.compose_protocols()
+  }
+
+  /**
+   * Compute the projection of the composition over a role w.r.t. the subscription, see ```projectCombineMachines```.
+   *
+   * @param role - A role (given as a string).
+   * @param minimize - The projection is minimized if ```minimize``` is true and returned as is otherwise.
+   */
+  projectCombineMachines(role: Role, minimize: boolean): DataResult<MachineType> {
+    return this.inner.project_combine(role, minimize)
+  }
+
+  free(): void {
+    this.inner.free()
+  }
+}
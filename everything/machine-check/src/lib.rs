@@ -6,11 +6,14 @@ mod machine;
 mod swarm;
 pub mod types;
 pub mod composition;
+pub mod render;
+pub mod dsl;
 
 use petgraph::visit::GraphBase;
-use types::{CheckResult, EventType, MachineLabel, ProtocolType, Role, State, SwarmLabel};
+use types::{CheckResult, ErrorInfo, EventType, MachineLabel, ProtocolType, Role, State, SwarmLabel};
 
-use crate::types::DataResult;
+use crate::render::RenderFormat;
+use crate::types::{DataResult, ErrorCode, Transition};
 
 #[declare]
 pub type Subscriptions = BTreeMap<Role, BTreeSet<EventType>>;
@@ -20,6 +23,7 @@ pub type SwarmProtocolType = ProtocolType<SwarmLabel>;
 pub type MachineType = ProtocolType<MachineLabel>;
 
 pub type Graph = petgraph::Graph<State, SwarmLabel>;
+type MachineGraph = petgraph::Graph<Option<State>, MachineLabel>;
 pub type NodeId = <petgraph::Graph<(), ()> as GraphBase>::NodeId;
 pub type EdgeId = <petgraph::Graph<(), ()> as GraphBase>::EdgeId;
 
@@ -27,68 +31,105 @@ pub type EdgeId = <petgraph::Graph<(), ()> as GraphBase>::EdgeId;
 pub fn check_swarm(proto: SwarmProtocolType, subs: String) -> CheckResult {
     let subs = match serde_json::from_str::<Subscriptions>(&subs) {
         Ok(p) => p,
-        Err(e) => return CheckResult::ERROR { errors: vec![format!("parsing subscriptions: {}", e)] },
+        Err(e) => return CheckResult::from_errors(vec![ErrorInfo::invalid_subscriptions(e)]),
     };
     let (graph, _, errors) = swarm::check(proto, &subs);
-    if errors.is_empty() {
-        CheckResult::OK
-    } else {
-        CheckResult::ERROR { errors: errors.map(swarm::Error::convert(&graph)) }
-    }
+    CheckResult::from_errors(errors.iter().map(|e| e.to_info(&graph)).collect())
 }
 
 #[wasm_bindgen]
 pub fn well_formed_sub(proto: SwarmProtocolType, subs: String) -> DataResult<Subscriptions> {
     let subs = match serde_json::from_str::<Subscriptions>(&subs) {
         Ok(p) => p,
-        Err(e) => return DataResult::ERROR { errors: vec![format!("parsing subscriptions: {}", e)] },
+        Err(e) => return DataResult::from_errors(vec![ErrorInfo::invalid_subscriptions(e)]),
     };
     match swarm::well_formed_sub(proto, &subs) {
         Ok(subscriptions) => DataResult::OK {
             data: subscriptions,
         },
-        Err((graph, _, errors)) => DataResult::ERROR {
-            errors: errors.map(swarm::Error::convert(&graph))
-        },
+        Err((graph, _, errors)) => {
+            DataResult::from_errors(errors.iter().map(|e| e.to_info(&graph)).collect())
+        }
     }
 }
 
 #[wasm_bindgen]
 pub fn check_projection(swarm: SwarmProtocolType, subs: String, role: Role, machine: MachineType) -> CheckResult {
+    check_machine(swarm, subs, role, machine, |proj, proj_initial, machine, initial| {
+        machine::equivalent(proj, proj_initial, machine, initial)
+            .iter()
+            .map(|e| e.to_info(proj, proj_initial, machine, initial))
+            .collect()
+    })
+}
+
+// Like check_projection() but machine may leave out commands of the projection, see machine::refines().
+#[wasm_bindgen]
+pub fn check_refinement(swarm: SwarmProtocolType, subs: String, role: Role, machine: MachineType) -> CheckResult {
+    check_machine(swarm, subs, role, machine, |proj, proj_initial, machine, initial| {
+        machine::refines(proj, proj_initial, machine, initial)
+            .iter()
+            .map(|v| v.to_info())
+            .collect()
+    })
+}
+
+// Project swarm onto role and compare machine with the projection, see machine::check_against_projection().
+fn check_machine(
+    swarm: SwarmProtocolType,
+    subs: String,
+    role: Role,
+    machine: MachineType,
+    compare: impl FnOnce(&MachineGraph, NodeId, &MachineGraph, NodeId) -> Vec<ErrorInfo>,
+) -> CheckResult {
     let subs = match serde_json::from_str::<Subscriptions>(&subs) {
         Ok(p) => p,
-        Err(e) => return CheckResult::ERROR { errors: vec![format!("parsing subscriptions: {}", e)] },
+        Err(e) => return CheckResult::from_errors(vec![ErrorInfo::invalid_subscriptions(e)]),
     };
 
     let (swarm, initial, mut errors) = swarm::from_json(swarm, &subs);
     let Some(initial) = initial else {
-        return CheckResult::ERROR { errors: errors };
+        return CheckResult::from_errors(errors);
     };
     let (proj, proj_initial) = machine::project(&swarm, initial, &subs, role);
-    let (machine, json_initial, m_errors) = machine::from_json(machine);
-    let machine_problem = !m_errors.is_empty();
-    errors.extend(m_errors);
-    let Some(json_initial) = json_initial else {
-        errors.push(format!("initial machine state has no transitions"));
-        return CheckResult::ERROR { errors: errors };
+    errors.extend(machine::check_against_projection(&proj, proj_initial, machine, compare));
+
+    CheckResult::from_errors(errors)
+}
+
+#[wasm_bindgen]
+pub fn render_swarm(proto: SwarmProtocolType, format: RenderFormat, highlight: String) -> DataResult<String> {
+    let highlight = match serde_json::from_str::<Vec<Transition<SwarmLabel>>>(&highlight) {
+        Ok(h) => h,
+        Err(e) => return DataResult::from_errors(vec![ErrorInfo::new(ErrorCode::ParseError, format!("parsing highlighted transitions: {}", e))]),
     };
-    if machine_problem {
-        return CheckResult::ERROR { errors: errors };
-    }
+    DataResult::OK { data: render::render(&proto, format, &highlight) }
+}
 
-    errors.extend(
-        machine::equivalent(&proj, proj_initial, &machine, json_initial)
-            .into_iter()
-            .map(machine::Error::convert(&proj, &machine)),
-    );
+#[wasm_bindgen]
+pub fn render_machine(machine: MachineType, format: RenderFormat, highlight: String) -> DataResult<String> {
+    let highlight = match serde_json::from_str::<Vec<Transition<MachineLabel>>>(&highlight) {
+        Ok(h) => h,
+        Err(e) => return DataResult::from_errors(vec![ErrorInfo::new(ErrorCode::ParseError, format!("parsing highlighted transitions: {}", e))]),
+    };
+    DataResult::OK { data: render::render(&machine, format, &highlight) }
+}
 
-    if errors.is_empty() {
-        CheckResult::OK
-    } else {
-        CheckResult::ERROR { errors: errors }
+#[wasm_bindgen]
+pub fn parse_protocols(src: String) -> DataResult<dsl::ParsedProtocols> {
+    match dsl::parse(&src) {
+        Ok(spec) => DataResult::OK {
+            data: dsl::ParsedProtocols { protocols: spec.protocols, subscriptions: spec.subscriptions },
+        },
+        Err(e) => DataResult::from_errors(vec![e.to_info()]),
     }
 }
 
+#[wasm_bindgen]
+pub fn check_protocol_source(src: String) -> CheckResult {
+    CheckResult::from_errors(dsl::check(&src))
+}
+
 trait MapVec<T> {
     fn map<U>(self, f: impl Fn(T) -> U) -> Vec<U>;
 }
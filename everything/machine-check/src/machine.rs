@@ -1,5 +1,6 @@
 use crate::{
-    types::{Command, EventType, MachineLabel, Role, State},
+    composition::composition_types::{RefinementViolation, RefinementViolationKind},
+    types::{Command, ErrorCode, ErrorInfo, EventType, MachineLabel, Role, State},
     EdgeId, MachineType, NodeId, Subscriptions,
 };
 use itertools::Itertools;
@@ -9,7 +10,7 @@ use petgraph::{
 };
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fmt,
     iter::once,
 };
@@ -92,7 +93,7 @@ pub fn project(
     (machine, m_nodes[initial.index()])
 }
 
-pub fn from_json(proto: MachineType) -> (Graph, Option<NodeId>, Vec<String>) {
+pub fn from_json(proto: MachineType) -> (Graph, Option<NodeId>, Vec<ErrorInfo>) {
     let _span = tracing::debug_span!("from_json").entered();
     let mut errors = Vec::new();
     let mut machine = Graph::new();
@@ -106,16 +107,46 @@ pub fn from_json(proto: MachineType) -> (Graph, Option<NodeId>, Vec<String>) {
             .entry(t.target.clone())
             .or_insert_with(|| machine.add_node(Some(t.target)));
         if let (MachineLabel::Execute { cmd, .. }, true) = (&t.label, source != target) {
-            errors.push(format!(
-                "command {cmd} is not a self-loop in state {}",
-                t.source
-            ));
+            let mut info = ErrorInfo::new(
+                ErrorCode::InvalidMachine,
+                format!("command {cmd} is not a self-loop in state {}", t.source),
+            );
+            info.add_command(cmd);
+            info.add_state(&t.source);
+            errors.push(info);
         }
         machine.add_edge(source, target, t.label);
     }
     (machine, nodes.get(&proto.initial).copied(), errors)
 }
 
+// Parse a machine, reporting the problems found or a missing initial state.
+pub fn checked_from_json(proto: MachineType) -> Result<(Graph, NodeId), Vec<ErrorInfo>> {
+    let (machine, initial, mut errors) = from_json(proto);
+    match initial {
+        Some(initial) if errors.is_empty() => Ok((machine, initial)),
+        Some(_) => Err(errors),
+        None => {
+            errors.push(ErrorInfo::no_initial_machine_state());
+            Err(errors)
+        }
+    }
+}
+
+// Compare a machine with the projection proj of a protocol using equivalent() or refines(),
+// or report the problems with the machine itself.
+pub fn check_against_projection(
+    proj: &Graph,
+    proj_initial: NodeId,
+    machine: MachineType,
+    compare: impl FnOnce(&Graph, NodeId, &Graph, NodeId) -> Vec<ErrorInfo>,
+) -> Vec<ErrorInfo> {
+    match checked_from_json(machine) {
+        Ok((machine, initial)) => compare(proj, proj_initial, &machine, initial),
+        Err(errors) => errors,
+    }
+}
+
 pub enum Side {
     Left,
     Right,
@@ -168,9 +199,110 @@ impl Error {
         }
     }
 
+    // Errors as strings, see to_info() for structured errors.
+    #[allow(dead_code)]
     pub fn convert<'a>(left: &'a Graph, right: &'a Graph) -> impl Fn(Error) -> String + 'a {
         |err| err.to_string(left, right)
     }
+
+    // Structured version of to_string(). States are the state of the specimen (right) the error
+    // concerns and for missing transitions also the state of the reference it corresponds to.
+    // li and ri are the initial states, missing transitions come with a shortest trace from them,
+    // which is also appended to the message unless it is empty.
+    pub fn to_info(&self, left: &Graph, li: NodeId, right: &Graph, ri: NodeId) -> ErrorInfo {
+        let (code, graph, state, edge, from) = match self {
+            Error::NonDeterministic(Side::Left, edge) => {
+                (ErrorCode::NonDeterministicTransition, left, None, *edge, None)
+            }
+            Error::NonDeterministic(Side::Right, edge) => {
+                (ErrorCode::NonDeterministicTransition, right, None, *edge, None)
+            }
+            Error::MissingTransition(Side::Left, l_node, r_edge) => (
+                ErrorCode::ExtraneousTransition,
+                right,
+                Some(state_name(left, *l_node)),
+                *r_edge,
+                None,
+            ),
+            Error::MissingTransition(Side::Right, r_node, l_edge) => (
+                ErrorCode::MissingTransition,
+                left,
+                Some(state_name(right, *r_node)),
+                *l_edge,
+                left.edge_endpoints(*l_edge).map(|(from, _)| state_name(left, from)),
+            ),
+        };
+        let state = state.or_else(|| {
+            graph
+                .edge_endpoints(edge)
+                .map(|(source, _)| state_name(graph, source))
+        });
+        let mut info = ErrorInfo::new(code, self.to_string(left, right));
+        for s in state.into_iter().chain(from) {
+            info.add_state(&State::new(&s.to_string()));
+        }
+        match graph.edge_weight(edge) {
+            Some(MachineLabel::Execute { cmd, log_type }) => {
+                info.add_command(cmd);
+                log_type.iter().for_each(|t| info.add_event_type(t));
+            }
+            Some(MachineLabel::Input { event_type }) => info.add_event_type(event_type),
+            None => (),
+        }
+        let nodes = match self {
+            Error::NonDeterministic(..) => None,
+            Error::MissingTransition(Side::Left, l_node, r_edge) => right
+                .edge_endpoints(*r_edge)
+                .map(|(r_node, _)| (*l_node, r_node)),
+            Error::MissingTransition(Side::Right, r_node, l_edge) => left
+                .edge_endpoints(*l_edge)
+                .map(|(l_node, _)| (l_node, *r_node)),
+        };
+        if let Some(trace) = nodes.and_then(|target| shortest_trace(left, li, right, ri, target)) {
+            if !trace.is_empty() {
+                let labels = trace.iter().map(|l| l.to_string()).join(", ");
+                info.message = format!("{} after [{}]", info.message, labels);
+            }
+            info.trace = trace;
+        }
+        info
+    }
+}
+
+/// Shortest sequence of labels leading from (li, ri) to the pair of states target, taking
+/// transitions with the same label on both sides. None if target is not reachable this way.
+pub fn shortest_trace(
+    left: &Graph,
+    li: NodeId,
+    right: &Graph,
+    ri: NodeId,
+    target: (NodeId, NodeId),
+) -> Option<Vec<MachineLabel>> {
+    let mut predecessors: BTreeMap<_, Option<(_, &MachineLabel)>> =
+        BTreeMap::from([((li, ri), None)]);
+    let mut queue = VecDeque::from([(li, ri)]);
+    while let Some(pair) = queue.pop_front() {
+        if pair == target {
+            let mut trace = vec![];
+            let mut current = pair;
+            while let Some(Some((previous, label))) = predecessors.get(&current) {
+                trace.push((*label).clone());
+                current = *previous;
+            }
+            trace.reverse();
+            return Some(trace);
+        }
+        for l_edge in left.edges_directed(pair.0, Outgoing) {
+            for r_edge in right.edges_directed(pair.1, Outgoing) {
+                let next = (l_edge.target(), r_edge.target());
+                if l_edge.weight() == r_edge.weight() && !predecessors.contains_key(&next) {
+                    predecessors.insert(next, Some((pair, l_edge.weight())));
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    None
 }
 
 struct StatePrinter<'a>(Option<&'a State>, u32);
@@ -312,11 +444,131 @@ pub fn equivalent(left: &Graph, li: NodeId, right: &Graph, ri: NodeId) -> Vec<Er
     errors
 }
 
+// Check that right refines the reference left: in every pair of corresponding states right must react to every
+// event type left reacts to and may only offer commands left offers, but may leave out commands of left.
+// Corresponding states are explored breadth first, so every violation comes with a shortest trace leading to it.
+pub fn refines(
+    left: &Graph,
+    li: NodeId,
+    right: &Graph,
+    ri: NodeId,
+) -> Vec<RefinementViolation> {
+    let _span = tracing::debug_span!("refines").entered();
+
+    let mut violations = Vec::new();
+    let mut queue = VecDeque::from([(li, ri, vec![])]);
+    let mut visited = BTreeSet::from([(li, ri)]);
+    while let Some((li, ri, trace)) = queue.pop_front() {
+        let violation = |kind, label: &MachineLabel| RefinementViolation {
+            kind,
+            trace: trace.clone(),
+            state: State::new(&state_name(right, ri).to_string()),
+            reference_state: State::new(&state_name(left, li).to_string()),
+            label: label.clone(),
+        };
+        let mut l_out = BTreeMap::new();
+        for edge in left.edges_directed(li, Outgoing) {
+            l_out
+                .entry(DeterministicLabel::from(edge.weight()))
+                .and_modify(|_| {
+                    violations.push(violation(
+                        RefinementViolationKind::NonDeterministic,
+                        edge.weight(),
+                    ))
+                })
+                .or_insert(edge);
+        }
+        let mut r_out = BTreeMap::new();
+        for edge in right.edges_directed(ri, Outgoing) {
+            r_out
+                .entry(DeterministicLabel::from(edge.weight()))
+                .and_modify(|_| {
+                    violations.push(violation(
+                        RefinementViolationKind::NonDeterministic,
+                        edge.weight(),
+                    ))
+                })
+                .or_insert(edge);
+        }
+
+        for (label, l_edge) in &l_out {
+            match (label, r_out.get(label)) {
+                (DeterministicLabel::Event(_), None) => violations.push(violation(
+                    RefinementViolationKind::MissingInput,
+                    l_edge.weight(),
+                )),
+                (DeterministicLabel::Command(_), None) => (),
+                (_, Some(r_edge)) if l_edge.weight() != r_edge.weight() => violations.push(
+                    violation(RefinementViolationKind::ExtraneousTransition, r_edge.weight()),
+                ),
+                (_, Some(r_edge)) => {
+                    if visited.insert((l_edge.target(), r_edge.target())) {
+                        let mut trace = trace.clone();
+                        trace.push(r_edge.weight().clone());
+                        queue.push_back((l_edge.target(), r_edge.target(), trace));
+                    }
+                }
+            }
+        }
+        for (label, r_edge) in &r_out {
+            if !l_out.contains_key(label) {
+                violations.push(violation(
+                    RefinementViolationKind::ExtraneousTransition,
+                    r_edge.weight(),
+                ));
+            }
+        }
+    }
+
+    violations
+}
+
+impl RefinementViolation {
+    pub fn to_info(&self) -> ErrorInfo {
+        let trace = self.trace.iter().map(|l| l.to_string()).join(", ");
+        let (code, message) = match self.kind {
+            RefinementViolationKind::MissingInput => (
+                ErrorCode::MissingTransition,
+                format!(
+                    "missing transition {} in state {} (from reference state {}) after [{}]",
+                    self.label, self.state, self.reference_state, trace
+                ),
+            ),
+            RefinementViolationKind::ExtraneousTransition => (
+                ErrorCode::ExtraneousTransition,
+                format!(
+                    "extraneous transition {} in state {} after [{}]",
+                    self.label, self.state, trace
+                ),
+            ),
+            RefinementViolationKind::NonDeterministic => (
+                ErrorCode::NonDeterministicTransition,
+                format!(
+                    "non-deterministic transition {} in state {} after [{}]",
+                    self.label, self.state, trace
+                ),
+            ),
+        };
+        let mut info = ErrorInfo::new(code, message);
+        info.add_state(&self.state);
+        info.add_state(&self.reference_state);
+        match &self.label {
+            MachineLabel::Execute { cmd, log_type } => {
+                info.add_command(cmd);
+                log_type.iter().for_each(|t| info.add_event_type(t));
+            }
+            MachineLabel::Input { event_type } => info.add_event_type(event_type),
+        }
+        info
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
     use tracing_subscriber::{fmt, fmt::format::FmtSpan, EnvFilter};
-    use crate::{types::{CheckResult, Role}, MachineType, SwarmProtocolType};
+    use super::*;
+    use crate::{types::{CheckResult, ErrorCode, EventType, MachineLabel, Role}, MachineType, SwarmProtocolType};
 
     fn setup_logger() {
         fmt()
@@ -379,11 +631,126 @@ mod tests {
         );
         let errors = match result {
             CheckResult::OK => vec![],
-            CheckResult::ERROR { errors } => errors
+            CheckResult::ERROR { errors, .. } => errors
         };
         assert_eq!(
             errors,
             ["guard event type Bid appears in transitions from multiple states"]
         );
     }
+
+    #[test]
+    fn missing_transition_trace() {
+        setup_logger();
+        let swarm = r#"{
+            "initial":"S0",
+            "transitions":[
+                {"source":"S0","target":"S1","label":{"role":"P","cmd":"Request","logType":["Requested"]}},
+                {"source":"S1","target":"S2","label":{"role":"T","cmd":"Offer","logType":["Bid"]}}
+            ]}"#;
+        let subs = r#"{ "P":["Requested","Bid"], "T":["Requested","Bid"] }"#;
+        let machine = r#"{
+            "initial":"S0",
+            "transitions":[
+                {"source":"S0","target":"S0","label":{"tag":"Execute","cmd":"Request","logType":["Requested"]}},
+                {"source":"S0","target":"S1","label":{"tag":"Input","eventType":"Requested"}}
+            ]}"#;
+
+        let result = crate::check_projection(
+            serde_json::from_str::<SwarmProtocolType>(swarm).unwrap(),
+            subs.to_owned(),
+            Role::new("P"),
+            serde_json::from_str::<MachineType>(machine).unwrap(),
+        );
+        let details = match result {
+            CheckResult::OK => vec![],
+            CheckResult::ERROR { details, .. } => details
+        };
+        assert_eq!(details.len(), 1);
+        assert_eq!(details[0].code, ErrorCode::MissingTransition);
+        assert_eq!(
+            details[0].trace,
+            vec![MachineLabel::Input { event_type: EventType::new("Requested") }]
+        );
+        assert_eq!(
+            details[0].message,
+            "missing transition Bid? in state S1 (from reference state S1) after [Requested?]"
+        );
+    }
+
+    #[test]
+    fn refinement() {
+        setup_logger();
+        let swarm = r#"{
+            "initial":"0",
+            "transitions":[
+                {"source":"0","target":"1","label":{"role":"T","cmd":"request","logType":["partID"]}},
+                {"source":"1","target":"2","label":{"role":"FL","cmd":"get","logType":["pos"]}},
+                {"source":"2","target":"0","label":{"role":"T","cmd":"deliver","logType":["part"]}},
+                {"source":"0","target":"3","label":{"role":"D","cmd":"close","logType":["time"]}}
+            ]}"#;
+        let subs = serde_json::from_str::<Subscriptions>(r#"{ "T":["partID","pos","part","time"] }"#).unwrap();
+        let (swarm, initial, errors) =
+            crate::swarm::from_json(serde_json::from_str::<SwarmProtocolType>(swarm).unwrap(), &subs);
+        assert!(errors.is_empty());
+        let (proj, initial) = project(&swarm, initial.unwrap(), &subs, Role::new("T"));
+        assert!(refines(&proj, initial, &proj, initial).is_empty());
+
+        let edges = |graph: &Graph, label: &MachineLabel| {
+            graph
+                .edge_indices()
+                .filter(|e| graph[*e] == *label)
+                .collect::<Vec<_>>()
+        };
+        let deliver = MachineLabel::Execute {
+            cmd: Command::new("deliver"),
+            log_type: vec![EventType::new("part")],
+        };
+        let pos = MachineLabel::Input {
+            event_type: EventType::new("pos"),
+        };
+
+        // T never delivering is not equivalent to the projection but refines it.
+        let mut machine = proj.clone();
+        for edge in edges(&machine, &deliver).into_iter().rev() {
+            machine.remove_edge(edge);
+        }
+        assert!(!equivalent(&proj, initial, &machine, initial).is_empty());
+        assert!(refines(&proj, initial, &machine, initial).is_empty());
+
+        // T ignoring pos.
+        let mut machine = proj.clone();
+        for edge in edges(&machine, &pos).into_iter().rev() {
+            machine.remove_edge(edge);
+        }
+        let violations = refines(&proj, initial, &machine, initial);
+        assert!(!violations.is_empty());
+        assert!(violations
+            .iter()
+            .all(|v| v.kind == RefinementViolationKind::MissingInput && v.label == pos));
+        assert_eq!(
+            violations[0].trace,
+            vec![MachineLabel::Input {
+                event_type: EventType::new("partID")
+            }]
+        );
+        assert_eq!(violations[0].to_info().code, ErrorCode::MissingTransition);
+
+        // T closing.
+        let mut machine = proj.clone();
+        let close = MachineLabel::Execute {
+            cmd: Command::new("close"),
+            log_type: vec![EventType::new("time")],
+        };
+        machine.add_edge(initial, initial, close.clone());
+        let violations = refines(&proj, initial, &machine, initial);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].kind,
+            RefinementViolationKind::ExtraneousTransition
+        );
+        assert_eq!(violations[0].label, close);
+        assert!(violations[0].trace.is_empty());
+        assert!(violations[0].to_info().message.ends_with("after []"));
+    }
 }
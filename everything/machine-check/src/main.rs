@@ -0,0 +1,245 @@
+use machine_check::{
+    composition::{
+        check_composed_projection, check_composed_swarm, check_parsed_event_log, compose_protocols,
+        composition_types::{Granularity, InterfacingProtocols},
+        exact_well_formed_sub, minimize_machine, overapproximated_well_formed_sub, parse_log,
+        project_combine,
+    },
+    dsl,
+    render::{render, RenderFormat},
+    types::{CheckResult, DataResult, Role},
+    MachineType, Subscriptions,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, process::ExitCode};
+
+const USAGE: &str = "usage: machine-check <command> [arguments]
+
+commands:
+  check <protocols> <subscriptions>
+      check that the composition of protocols is well-formed w.r.t. subscriptions
+  exact-sub <protocols> [<subscriptions>]
+      generate the smallest well-formed subscription containing subscriptions
+  overapprox-sub <protocols> [<subscriptions>] [--granularity fine|medium|coarse|two-step]
+      generate a well-formed subscription compositionally (default granularity: two-step)
+  project <protocols> <subscriptions> <role> [--minimize]
+      compute the projection of the composition over role
+  check-projection <protocols> <subscriptions> <role> <machine>
+      check that machine implements role of the composition
+  compose <protocols>
+      compute the composition of protocols
+  monitor <protocols> <log>
+      check that a recorded event log conforms to the composition of protocols,
+      reporting the first violating event, the state before it and the allowed events
+  check-source <source>
+      check a composition and subscription written in the textual protocol syntax,
+      errors are printed as <source>:<line>:<column>: <message>
+  render <protocols> [<subscriptions>] [--format dot|mermaid]
+      print the composition of protocols as a state diagram (default format: dot),
+      highlighting the transitions violating well-formedness w.r.t. subscriptions
+  render-machine <machine> [--format dot|mermaid]
+      print machine as a state diagram
+  minimize-machine <machine>
+      compute the minimal deterministic machine equivalent to machine
+
+<protocols> is a JSON file containing an array of swarm protocols, <subscriptions>
+a JSON file containing a subscription and <machine> a JSON file containing a machine.
+<log> is a JSON lines file with one event per line, an object with the fields eventType, role and
+optionally payload.
+Except for the render commands the result is printed as JSON. The exit code is 0 on success, 1 if the check or
+computation reported errors and 2 if the arguments or input files are invalid.";
+
+// Exit code when checks fail or errors are reported by the computation.
+const EXIT_ERRORS: u8 = 1;
+// Exit code for invalid arguments or unreadable input.
+const EXIT_USAGE: u8 = 2;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => ExitCode::from(code),
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            ExitCode::from(EXIT_USAGE)
+        }
+    }
+}
+
+// Run the command given by args and print its result. Returns the exit code.
+fn run(args: &[String]) -> Result<u8, String> {
+    let Some((command, args)) = args.split_first() else {
+        return Err("missing command".to_string());
+    };
+    let mut positional = vec![];
+    let mut minimize = false;
+    let mut granularity = Granularity::TwoStep;
+    let mut format = RenderFormat::Dot;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--minimize" => minimize = true,
+            "--granularity" => granularity = parse_granularity(args.next())?,
+            "--format" => format = parse_format(args.next())?,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            _ => positional.push(arg),
+        }
+    }
+    let positional = |i: usize, name: &str| -> Result<&String, String> {
+        positional
+            .get(i)
+            .copied()
+            .ok_or_else(|| format!("missing argument <{name}> for {command}"))
+    };
+
+    match command.as_str() {
+        "check" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let subs = read_subscriptions(Some(positional(1, "subscriptions")?))?;
+            print_check_result(check_composed_swarm(protos, subs))
+        }
+        "exact-sub" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let subs = read_subscriptions(positional(1, "subscriptions").ok())?;
+            print_data_result(exact_well_formed_sub(protos, subs))
+        }
+        "overapprox-sub" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let subs = read_subscriptions(positional(1, "subscriptions").ok())?;
+            print_data_result(overapproximated_well_formed_sub(protos, subs, granularity))
+        }
+        "project" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let subs = read_subscriptions(Some(positional(1, "subscriptions")?))?;
+            let role = Role::new(positional(2, "role")?);
+            print_data_result(project_combine(protos, subs, role, minimize))
+        }
+        "check-projection" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let subs = read_subscriptions(Some(positional(1, "subscriptions")?))?;
+            let role = Role::new(positional(2, "role")?);
+            let machine = read_json::<MachineType>(positional(3, "machine")?)?;
+            print_check_result(check_composed_projection(protos, subs, role, machine))
+        }
+        "compose" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            print_data_result(compose_protocols(protos))
+        }
+        "monitor" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let path = positional(1, "log")?;
+            let log = fs::read_to_string(path).map_err(|e| format!("reading {path}: {e}"))?;
+            let log = parse_log(&log).map_err(|e| format!("parsing {path}: {e}"))?;
+            let result = check_parsed_event_log(protos, &log);
+            let code = match &result {
+                DataResult::OK { data } if data.violation.is_none() => 0,
+                _ => EXIT_ERRORS,
+            };
+            print_json(&result)?;
+            Ok(code)
+        }
+        "check-source" => {
+            let path = positional(0, "source")?;
+            let src = fs::read_to_string(path).map_err(|e| format!("reading {path}: {e}"))?;
+            let errors = dsl::check(&src);
+            for e in &errors {
+                match e.span {
+                    Some(span) => println!("{path}:{span}: {}", e.message),
+                    None => println!("{path}: {}", e.message),
+                }
+            }
+            Ok(if errors.is_empty() { 0 } else { EXIT_ERRORS })
+        }
+        "render" => {
+            let protos = read_json::<InterfacingProtocols>(positional(0, "protocols")?)?;
+            let highlight = match positional(1, "subscriptions") {
+                Ok(path) => match check_composed_swarm(protos.clone(), read_subscriptions(Some(path))?) {
+                    CheckResult::OK => vec![],
+                    CheckResult::ERROR { details, .. } => {
+                        details.into_iter().flat_map(|e| e.transitions).collect()
+                    }
+                },
+                Err(_) => vec![],
+            };
+            match compose_protocols(protos) {
+                DataResult::OK { data } => {
+                    print!("{}", render(&data, format, &highlight));
+                    Ok(0)
+                }
+                result => print_data_result(result),
+            }
+        }
+        "render-machine" => {
+            let machine = read_json::<MachineType>(positional(0, "machine")?)?;
+            print!("{}", render(&machine, format, &[]));
+            Ok(0)
+        }
+        "minimize-machine" => {
+            let machine = read_json::<MachineType>(positional(0, "machine")?)?;
+            print_data_result(minimize_machine(machine))
+        }
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(0)
+        }
+        _ => Err(format!("unknown command {command}")),
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("reading {path}: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("parsing {path}: {e}"))
+}
+
+// The wasm entry points take subscriptions as a JSON string. Read and validate the file,
+// an absent file means the empty subscription.
+fn read_subscriptions(path: Option<&String>) -> Result<String, String> {
+    let subs = match path {
+        Some(path) => read_json::<Subscriptions>(path)?,
+        None => Subscriptions::new(),
+    };
+    serde_json::to_string(&subs).map_err(|e| e.to_string())
+}
+
+fn parse_granularity(value: Option<&String>) -> Result<Granularity, String> {
+    match value.map(|s| s.as_str()) {
+        Some("fine") => Ok(Granularity::Fine),
+        Some("medium") => Ok(Granularity::Medium),
+        Some("coarse") => Ok(Granularity::Coarse),
+        Some("two-step") => Ok(Granularity::TwoStep),
+        Some(other) => Err(format!("unknown granularity {other}")),
+        None => Err("missing value for --granularity".to_string()),
+    }
+}
+
+fn parse_format(value: Option<&String>) -> Result<RenderFormat, String> {
+    match value.map(|s| s.as_str()) {
+        Some("dot") => Ok(RenderFormat::Dot),
+        Some("mermaid") => Ok(RenderFormat::Mermaid),
+        Some(other) => Err(format!("unknown format {other}")),
+        None => Err("missing value for --format".to_string()),
+    }
+}
+
+fn print_check_result(result: CheckResult) -> Result<u8, String> {
+    let code = match result {
+        CheckResult::OK => 0,
+        CheckResult::ERROR { .. } => EXIT_ERRORS,
+    };
+    print_json(&result)?;
+    Ok(code)
+}
+
+fn print_data_result<T: Serialize>(result: DataResult<T>) -> Result<u8, String> {
+    let code = match result {
+        DataResult::OK { .. } => 0,
+        DataResult::ERROR { .. } => EXIT_ERRORS,
+    };
+    print_json(&result)?;
+    Ok(code)
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
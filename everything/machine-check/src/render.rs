@@ -0,0 +1,228 @@
+use crate::types::{ProtocolType, State, Transition};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Display, Write},
+};
+use tsify::Tsify;
+
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum RenderFormat {
+    Dot,
+    Mermaid,
+}
+
+// Render a swarm protocol or a machine as a state diagram. Transitions in highlight,
+// e.g. the transitions referenced by the errors of a failed check, are drawn in red.
+// Labels are printed using the Display impls of SwarmLabel and MachineLabel.
+pub fn render<L: Display + PartialEq>(
+    proto: &ProtocolType<L>,
+    format: RenderFormat,
+    highlight: &[Transition<L>],
+) -> String {
+    let _span = tracing::info_span!("render").entered();
+    match format {
+        RenderFormat::Dot => to_dot(proto, highlight),
+        RenderFormat::Mermaid => to_mermaid(proto, highlight),
+    }
+}
+
+pub fn to_dot<L: Display + PartialEq>(proto: &ProtocolType<L>, highlight: &[Transition<L>]) -> String {
+    let mut out = String::new();
+    let highlighted = |t: &Transition<L>| highlight.contains(t);
+    writeln!(out, "digraph {{").unwrap();
+    writeln!(out, "    __start [shape=point];").unwrap();
+    for state in in_order(&states(proto)) {
+        writeln!(out, "    {} [shape=ellipse];", dot_id(state)).unwrap();
+    }
+    writeln!(out, "    __start -> {};", dot_id(&proto.initial)).unwrap();
+    for t in &proto.transitions {
+        let style = if highlighted(t) {
+            ", color=red, fontcolor=red, penwidth=2"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "    {} -> {} [label={}{style}];",
+            dot_id(&t.source),
+            dot_id(&t.target),
+            dot_id(&t.label.to_string())
+        )
+        .unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+// Mermaid state diagrams can not style individual transitions,
+// so the source and target states of highlighted transitions are styled instead.
+pub fn to_mermaid<L: Display + PartialEq>(
+    proto: &ProtocolType<L>,
+    highlight: &[Transition<L>],
+) -> String {
+    let mut out = String::new();
+    let states = states(proto);
+    let id = |state: &State| format!("s{}", states[state]);
+    writeln!(out, "stateDiagram-v2").unwrap();
+    for state in in_order(&states) {
+        writeln!(out, "    state \"{}\" as {}", mermaid_escape(state), id(state)).unwrap();
+    }
+    writeln!(out, "    [*] --> {}", id(&proto.initial)).unwrap();
+    for t in &proto.transitions {
+        writeln!(
+            out,
+            "    {} --> {} : {}",
+            id(&t.source),
+            id(&t.target),
+            mermaid_escape(&t.label.to_string())
+        )
+        .unwrap();
+    }
+    let highlighted: BTreeSet<usize> = proto
+        .transitions
+        .iter()
+        .filter(|t| highlight.contains(t))
+        .flat_map(|t| [states[&t.source], states[&t.target]])
+        .collect();
+    if !highlighted.is_empty() {
+        writeln!(out, "    classDef highlight stroke:red,stroke-width:3px,color:red").unwrap();
+        let ids = highlighted.iter().map(|i| format!("s{i}")).join(",");
+        writeln!(out, "    class {ids} highlight").unwrap();
+    }
+    out
+}
+
+// All states of the protocol, numbered in order of first appearance starting with the initial state.
+fn states<L>(proto: &ProtocolType<L>) -> BTreeMap<State, usize> {
+    let mut states = BTreeMap::new();
+    let all = [&proto.initial]
+        .into_iter()
+        .chain(proto.transitions.iter().flat_map(|t| [&t.source, &t.target]));
+    for state in all {
+        let next = states.len();
+        states.entry(state.clone()).or_insert(next);
+    }
+    states
+}
+
+fn in_order(states: &BTreeMap<State, usize>) -> Vec<&State> {
+    let mut ordered: Vec<_> = states.iter().collect();
+    ordered.sort_by_key(|(_, i)| **i);
+    ordered.into_iter().map(|(state, _)| state).collect()
+}
+
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Characters that would be interpreted by the mermaid parser are written as entity codes.
+fn mermaid_escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '"' => "#quot;".to_string(),
+            '<' => "#lt;".to_string(),
+            '>' => "#gt;".to_string(),
+            ';' => "#59;".to_string(),
+            ':' => "#58;".to_string(),
+            '#' => "#35;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{Command, EventType, Role, SwarmLabel},
+        MachineType, SwarmProtocolType,
+    };
+
+    fn get_proto() -> SwarmProtocolType {
+        let mut proto = serde_json::from_str::<SwarmProtocolType>(
+            r#"{
+                "initial": "0 || 0",
+                "transitions": [
+                    { "source": "0 || 0", "target": "1 || 0", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+                    { "source": "1 || 0", "target": "0 || 0", "label": { "cmd": "deliver", "logType": ["part", "pos"], "role": "T" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        // state names with quotes can not be deserialized from borrowed strings
+        proto.transitions.push(Transition {
+            label: SwarmLabel {
+                cmd: Command::new("close"),
+                log_type: vec![EventType::new("time")],
+                role: Role::new("D"),
+            },
+            source: State::new("0 || 0"),
+            target: State::new("\"done\""),
+        });
+        proto
+    }
+
+    #[test]
+    fn test_dot() {
+        let proto = get_proto();
+        let highlight = vec![proto.transitions[1].clone()];
+        assert_eq!(
+            render(&proto, RenderFormat::Dot, &highlight),
+            r#"digraph {
+    __start [shape=point];
+    "0 || 0" [shape=ellipse];
+    "1 || 0" [shape=ellipse];
+    "\"done\"" [shape=ellipse];
+    __start -> "0 || 0";
+    "0 || 0" -> "1 || 0" [label="request@T<partID>"];
+    "1 || 0" -> "0 || 0" [label="deliver@T<part,pos>", color=red, fontcolor=red, penwidth=2];
+    "0 || 0" -> "\"done\"" [label="close@D<time>"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_mermaid() {
+        let proto = get_proto();
+        let highlight = vec![proto.transitions[2].clone()];
+        assert_eq!(
+            render(&proto, RenderFormat::Mermaid, &highlight),
+            r##"stateDiagram-v2
+    state "0 || 0" as s0
+    state "1 || 0" as s1
+    state "#quot;done#quot;" as s2
+    [*] --> s0
+    s0 --> s1 : request@T#lt;partID#gt;
+    s1 --> s0 : deliver@T#lt;part,pos#gt;
+    s0 --> s2 : close@D#lt;time#gt;
+    classDef highlight stroke:red,stroke-width:3px,color:red
+    class s0,s2 highlight
+"##
+        );
+
+        let machine = serde_json::from_str::<MachineType>(
+            r#"{
+                "initial": "S0",
+                "transitions": [
+                    { "source": "S0", "target": "S0", "label": { "tag": "Execute", "cmd": "request", "logType": ["partID"] } },
+                    { "source": "S0", "target": "S1", "label": { "tag": "Input", "eventType": "partID" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            render(&machine, RenderFormat::Mermaid, &[]),
+            r##"stateDiagram-v2
+    state "S0" as s0
+    state "S1" as s1
+    [*] --> s0
+    s0 --> s0 : request/partID
+    s0 --> s1 : partID?
+"##
+        );
+    }
+}
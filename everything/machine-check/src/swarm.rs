@@ -1,6 +1,6 @@
 use crate::{
-    types::{EventType, Role, State, StateName, SwarmLabel},
-    EdgeId, MapVec, NodeId, Subscriptions, SwarmProtocolType,
+    types::{ErrorCode, ErrorInfo, EventType, Role, State, StateName, SwarmLabel, Transition},
+    EdgeId, NodeId, Subscriptions, SwarmProtocolType,
 };
 use bitvec::{bitvec, vec::BitVec};
 use itertools::Itertools;
@@ -103,6 +103,93 @@ impl Error {
     ) -> impl Fn(Error) -> String + '_ {
         |err| err.to_string(graph)
     }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::InitialStateDisconnected => ErrorCode::InitialStateDisconnected,
+            Error::StateUnreachable(_) => ErrorCode::StateUnreachable,
+            Error::LogTypeEmpty(_) => ErrorCode::LogTypeEmpty,
+            Error::ActiveRoleNotSubscribed(_) => ErrorCode::ActiveRoleNotSubscribed,
+            Error::LaterActiveRoleNotSubscribed(_, _) => ErrorCode::LaterActiveRoleNotSubscribed,
+            Error::LaterInvolvedRoleMoreSubscribed { .. } => {
+                ErrorCode::LaterInvolvedRoleMoreSubscribed
+            }
+            Error::LaterInvolvedNotGuarded(_, _) => ErrorCode::LaterInvolvedNotGuarded,
+            Error::NonDeterministicGuard(_) => ErrorCode::NonDeterministicGuard,
+            Error::NonDeterministicCommand(_) => ErrorCode::NonDeterministicCommand,
+            Error::GuardNotInvariant(_) => ErrorCode::GuardNotInvariant,
+        }
+    }
+
+    // Structured version of to_string(). Entities specific to the error come first,
+    // followed by the role, command and event types of the offending transition(s).
+    pub fn to_info<N: StateName>(&self, graph: &petgraph::Graph<N, SwarmLabel>) -> ErrorInfo {
+        let mut info = ErrorInfo::new(self.code(), self.to_string(graph));
+        match self {
+            Error::InitialStateDisconnected => (),
+            Error::StateUnreachable(node) => {
+                if let Some(state) = graph.node_weight(*node) {
+                    info.add_state(state.state_name());
+                }
+            }
+            Error::LogTypeEmpty(edge)
+            | Error::ActiveRoleNotSubscribed(edge)
+            | Error::NonDeterministicGuard(edge)
+            | Error::NonDeterministicCommand(edge) => {
+                info.transitions_from(graph, [*edge]);
+            }
+            Error::LaterActiveRoleNotSubscribed(edge, role)
+            | Error::LaterInvolvedNotGuarded(edge, role) => {
+                info.add_role(role);
+                info.transitions_from(graph, [*edge]);
+            }
+            Error::LaterInvolvedRoleMoreSubscribed {
+                edge,
+                later,
+                active,
+                events,
+            } => {
+                info.add_role(later);
+                info.add_role(active);
+                events.iter().for_each(|event_type| info.add_event_type(event_type));
+                info.transitions_from(graph, [*edge]);
+            }
+            Error::GuardNotInvariant(event_type) => {
+                info.add_event_type(event_type);
+                let guarded = graph
+                    .edge_references()
+                    .filter(|e| e.weight().log_type.first() == Some(event_type))
+                    .map(|e| e.id());
+                info.transitions_from(graph, guarded);
+            }
+        }
+        info
+    }
+}
+
+// The transition corresponding to an edge, if the edge exists.
+pub(crate) fn transition<N: StateName>(
+    graph: &petgraph::Graph<N, SwarmLabel>,
+    edge: EdgeId,
+) -> Option<Transition<SwarmLabel>> {
+    let (source, target) = graph.edge_endpoints(edge)?;
+    Some(Transition {
+        label: graph[edge].clone(),
+        source: graph[source].state_name().clone(),
+        target: graph[target].state_name().clone(),
+    })
+}
+
+impl ErrorInfo {
+    pub(crate) fn transitions_from<N: StateName>(
+        &mut self,
+        graph: &petgraph::Graph<N, SwarmLabel>,
+        edges: impl IntoIterator<Item = EdgeId>,
+    ) {
+        for t in edges.into_iter().filter_map(|edge| transition(graph, edge)) {
+            self.add_transition(t);
+        }
+    }
 }
 
 /// helper for printing a transition
@@ -414,9 +501,9 @@ fn wf_sub_step(graph: &mut Graph, initial: NodeId, subs: &mut Subscriptions) ->
 pub fn from_json(
     proto: SwarmProtocolType,
     subs: &Subscriptions,
-) -> (super::Graph, Option<NodeId>, Vec<String>) {
+) -> (super::Graph, Option<NodeId>, Vec<ErrorInfo>) {
     let (g, i, e) = prepare_graph(proto, subs);
-    (to_swarm(&g), i, e.map(Error::convert(&g)))
+    (to_swarm(&g), i, e.iter().map(|e| e.to_info(&g)).collect())
 }
 
 /// unfortunately there is no walker for neighbors, so we need to handroll it
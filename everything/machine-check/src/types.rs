@@ -1,6 +1,6 @@
 use intern_arc::{global::hash_interner, InternedHash};
 use serde::{Deserialize, Serialize};
-use std::{borrow::Borrow, fmt, ops::Deref};
+use std::{borrow::Borrow, collections::{BTreeMap, BTreeSet}, fmt, ops::Deref};
 use tsify::Tsify;
 
 macro_rules! decl_str {
@@ -69,7 +69,230 @@ decl_str!(EventType);
 #[tsify(into_wasm_abi)]
 pub enum CheckResult {
     OK,
-    ERROR { errors: Vec<String> },
+    ERROR {
+        errors: Vec<String>,
+        details: Vec<ErrorInfo>,
+    },
+}
+
+impl CheckResult {
+    pub fn from_errors(details: Vec<ErrorInfo>) -> Self {
+        if details.is_empty() {
+            CheckResult::OK
+        } else {
+            CheckResult::ERROR {
+                errors: details.iter().map(|e| e.message.clone()).collect(),
+                details,
+            }
+        }
+    }
+
+    /// The violations of the result up to the transitions they were found at, see [`ViolationKey`].
+    pub fn violation_keys(&self) -> BTreeSet<ViolationKey> {
+        match self {
+            CheckResult::OK => BTreeSet::new(),
+            CheckResult::ERROR { details, .. } => details
+                .iter()
+                .map(|info| {
+                    (
+                        info.code,
+                        info.transitions.first().and_then(|t| t.label.log_type.first().cloned()),
+                        info.roles.clone(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The code of an error together with the first event type of its first transition and its roles.
+/// The on-the-fly well-formedness check reports a violation once per key rather than once per transition.
+pub type ViolationKey = (ErrorCode, Option<EventType>, Vec<Role>);
+
+/// Stable identifier of an error, one per variant of the error enums plus input errors.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum ErrorCode {
+    InitialStateDisconnected,
+    StateUnreachable,
+    LogTypeEmpty,
+    ActiveRoleNotSubscribed,
+    LaterActiveRoleNotSubscribed,
+    LaterInvolvedRoleMoreSubscribed,
+    LaterInvolvedNotGuarded,
+    NonDeterministicGuard,
+    NonDeterministicCommand,
+    GuardNotInvariant,
+    InvalidInterfaceRole,
+    InterfaceEventNotInBothProtocols,
+    SpuriousInterface,
+    EventTypeOnDifferentLabels,
+    CommandOnDifferentLabels,
+    RoleNotSubscribedToBranch,
+    RoleNotSubscribedToJoin,
+    LoopingError,
+    /// No longer reported since commands emitting more than one event type are supported.
+    /// Kept so that the codes stay stable.
+    MoreThanOneEventTypeInCommand,
+    EventEmittedMultipleTimes,
+    CommandOnMultipleTransitions,
+    StateCanNotReachTerminal,
+    InvalidArg,
+    NonDeterministicTransition,
+    MissingTransition,
+    ExtraneousTransition,
+    InvalidSubscriptions,
+    InvalidMachine,
+    InvalidIndex,
+    ParseError,
+    ReservedCharacterInCommand,
+    Other,
+}
+
+impl ErrorCode {
+    // Every code reported so far fails the check, see Severity.
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+}
+
+/// Every reported error currently makes the result `ERROR`.
+/// `Warning` is reserved for findings that should be reported without failing the check.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Machine-readable description of an error. `message` is the same text as reported in `errors`.
+/// `component` is the index of the protocol in the input the error was found in,
+/// `None` for errors concerning the interfacing of protocols or the expanded composition.
+/// `trace` is a shortest sequence of transitions leading to the mismatch in both the reference and the tested machine,
+/// only for missing and extraneous transitions found when comparing machines. A non-empty trace is also appended to `message`.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct ErrorInfo {
+    pub code: ErrorCode,
+    pub severity: Severity,
+    pub message: String,
+    pub component: Option<usize>,
+    pub roles: Vec<Role>,
+    pub event_types: Vec<EventType>,
+    pub commands: Vec<Command>,
+    pub states: Vec<State>,
+    pub transitions: Vec<Transition<SwarmLabel>>,
+    pub witness: Option<WitnessInfo>,
+    pub span: Option<Span>,
+    pub trace: Vec<MachineLabel>,
+}
+
+impl ErrorInfo {
+    pub fn new(code: ErrorCode, message: String) -> Self {
+        Self {
+            code,
+            severity: code.severity(),
+            message,
+            component: None,
+            roles: vec![],
+            event_types: vec![],
+            commands: vec![],
+            states: vec![],
+            transitions: vec![],
+            witness: None,
+            span: None,
+            trace: vec![],
+        }
+    }
+
+    pub fn add_role(&mut self, role: &Role) {
+        if !self.roles.contains(role) {
+            self.roles.push(role.clone());
+        }
+    }
+
+    pub fn add_event_type(&mut self, event_type: &EventType) {
+        if !self.event_types.contains(event_type) {
+            self.event_types.push(event_type.clone());
+        }
+    }
+
+    pub fn add_command(&mut self, command: &Command) {
+        if !self.commands.contains(command) {
+            self.commands.push(command.clone());
+        }
+    }
+
+    pub fn add_state(&mut self, state: &State) {
+        if !self.states.contains(state) {
+            self.states.push(state.clone());
+        }
+    }
+
+    // Record a transition together with the role, command and event types of its label.
+    pub fn add_transition(&mut self, transition: Transition<SwarmLabel>) {
+        self.add_role(&transition.label.role);
+        self.add_command(&transition.label.cmd);
+        for event_type in &transition.label.log_type {
+            self.add_event_type(event_type);
+        }
+        self.transitions.push(transition);
+    }
+
+    pub fn no_initial_machine_state() -> Self {
+        Self::new(
+            ErrorCode::InvalidMachine,
+            "initial machine state has no transitions".to_string(),
+        )
+    }
+
+    pub fn invalid_subscriptions(e: serde_json::Error) -> Self {
+        Self::new(
+            ErrorCode::InvalidSubscriptions,
+            format!("parsing subscriptions: {}", e),
+        )
+    }
+}
+
+/// Location in a protocol source text. Lines and columns start at 1, the end column is exclusive.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    // The smallest span containing both spans.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+            end_line: other.end_line,
+            end_column: other.end_column,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Counterexample attached to determinacy errors: a path to the offending transition,
+/// two diverging logs and what each involved role observes of them.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct WitnessInfo {
+    pub path: Vec<Transition<SwarmLabel>>,
+    pub logs: Vec<Vec<EventType>>,
+    pub observed: BTreeMap<Role, Vec<Vec<EventType>>>,
 }
 
 #[derive(Tsify, Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -153,6 +376,20 @@ impl StateName for State {
 #[serde(tag = "type")]
 #[tsify(into_wasm_abi)]
 pub enum DataResult<T> {
-    OK { data: T },
-    ERROR { errors: Vec<String> },
+    OK {
+        data: T,
+    },
+    ERROR {
+        errors: Vec<String>,
+        details: Vec<ErrorInfo>,
+    },
+}
+
+impl<T> DataResult<T> {
+    pub fn from_errors(details: Vec<ErrorInfo>) -> Self {
+        DataResult::ERROR {
+            errors: details.iter().map(|e| e.message.clone()).collect(),
+            details,
+        }
+    }
 }
\ No newline at end of file
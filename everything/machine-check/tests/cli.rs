@@ -0,0 +1,198 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+// Example from coplaws slides
+static PROTOCOLS: &str = r#"[{
+    "initial": "0",
+    "transitions": [
+        { "source": "0", "target": "1", "label": { "cmd": "request", "logType": ["partID"], "role": "T" } },
+        { "source": "1", "target": "2", "label": { "cmd": "get", "logType": ["pos"], "role": "FL" } },
+        { "source": "2", "target": "0", "label": { "cmd": "deliver", "logType": ["part"], "role": "T" } },
+        { "source": "0", "target": "3", "label": { "cmd": "close", "logType": ["time"], "role": "D" } }
+    ]
+}]"#;
+
+static WF_SUBS: &str = r#"{
+    "T": ["partID", "part", "pos", "time"],
+    "FL": ["partID", "pos", "time"],
+    "D": ["partID", "part", "time"]
+}"#;
+
+static NOT_WF_SUBS: &str = r#"{ "T": ["partID"] }"#;
+
+// Write content to a file in a directory unique to the test.
+fn write_file(test: &str, name: &str, content: &str) -> String {
+    let dir: PathBuf = std::env::temp_dir().join(format!("machine-check-cli-{}-{test}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, content).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn run(args: &[&str]) -> (i32, serde_json::Value, Output) {
+    let output = Command::new(env!("CARGO_BIN_EXE_machine-check"))
+        .args(args)
+        .output()
+        .unwrap();
+    let json = serde_json::from_slice(&output.stdout).unwrap_or(serde_json::Value::Null);
+    (output.status.code().unwrap(), json, output)
+}
+
+#[test]
+fn check_exit_codes() {
+    let protos = write_file("check", "protos.json", PROTOCOLS);
+    let wf_subs = write_file("check", "wf_subs.json", WF_SUBS);
+    let not_wf_subs = write_file("check", "not_wf_subs.json", NOT_WF_SUBS);
+
+    let (code, json, _) = run(&["check", &protos, &wf_subs]);
+    assert_eq!(code, 0);
+    assert_eq!(json["type"], "OK");
+
+    let (code, json, _) = run(&["check", &protos, &not_wf_subs]);
+    assert_eq!(code, 1);
+    assert_eq!(json["type"], "ERROR");
+    assert!(!json["errors"].as_array().unwrap().is_empty());
+    assert_eq!(json["details"][0]["severity"], "Error");
+}
+
+#[test]
+fn subscriptions_and_projection() {
+    let protos = write_file("subs", "protos.json", PROTOCOLS);
+    let (code, exact, _) = run(&["exact-sub", &protos]);
+    assert_eq!(code, 0);
+    assert_eq!(exact["type"], "OK");
+
+    let (code, overapprox, _) = run(&["overapprox-sub", &protos, "--granularity", "coarse"]);
+    assert_eq!(code, 0);
+    assert_eq!(overapprox["type"], "OK");
+
+    let subs = write_file("subs", "subs.json", &exact["data"].to_string());
+    let (code, check, _) = run(&["check", &protos, &subs]);
+    assert_eq!(code, 0);
+    assert_eq!(check["type"], "OK");
+
+    let (code, projection, _) = run(&["project", &protos, &subs, "T", "--minimize"]);
+    assert_eq!(code, 0);
+    let machine = write_file("subs", "machine.json", &projection["data"].to_string());
+    let (code, check, _) = run(&["check-projection", &protos, &subs, "T", &machine]);
+    assert_eq!(code, 0);
+    assert_eq!(check["type"], "OK");
+    let (code, check, _) = run(&["check-projection", &protos, &subs, "FL", &machine]);
+    assert_eq!(code, 1);
+    assert_eq!(check["type"], "ERROR");
+
+    let (code, composition, _) = run(&["compose", &protos]);
+    assert_eq!(code, 0);
+    assert_eq!(composition["data"]["transitions"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn invalid_arguments() {
+    let protos = write_file("invalid", "protos.json", PROTOCOLS);
+    let (code, _, output) = run(&["check", &protos]);
+    assert_eq!(code, 2);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing argument <subscriptions>"));
+
+    let (code, _, _) = run(&["overapprox-sub", &protos, "--granularity", "exact"]);
+    assert_eq!(code, 2);
+    let (code, _, _) = run(&["check", &protos, "does-not-exist.json"]);
+    assert_eq!(code, 2);
+    let (code, _, _) = run(&["frobnicate"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn render_diagrams() {
+    let protos = write_file("render", "protos.json", PROTOCOLS);
+    let not_wf_subs = write_file("render", "not_wf_subs.json", NOT_WF_SUBS);
+    let (code, _, output) = run(&["render", &protos]);
+    assert_eq!(code, 0);
+    let dot = String::from_utf8_lossy(&output.stdout);
+    assert!(dot.starts_with("digraph {"));
+    assert!(dot.contains(r#""0" -> "1" [label="request@T<partID>"];"#));
+    assert!(!dot.contains("color=red"));
+
+    let (code, _, output) = run(&["render", &protos, &not_wf_subs, "--format", "mermaid"]);
+    assert_eq!(code, 0);
+    let mermaid = String::from_utf8_lossy(&output.stdout);
+    assert!(mermaid.starts_with("stateDiagram-v2"));
+    assert!(mermaid.contains("class "));
+}
+
+#[test]
+fn check_source() {
+    let ok = write_file(
+        "source",
+        "ok.swarm",
+        "component {\n    0 --[request@T<partID>]--> 1\n}\nsubscriptions {\n    T: partID\n}\n",
+    );
+    let (code, _, output) = run(&["check-source", &ok]);
+    assert_eq!(code, 0);
+    assert!(output.stdout.is_empty());
+
+    let not_ok = write_file(
+        "source",
+        "not_ok.swarm",
+        "component {\n    0 --[request@T<partID>]--> 1\n    1 --[get@FL<pos>]--> 2\n}\n",
+    );
+    let (code, _, output) = run(&["check-source", &not_ok]);
+    assert_eq!(code, 1);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("{not_ok}:2:5: active role does not subscribe")));
+}
+
+#[test]
+fn monitor_log() {
+    let protos = write_file("monitor", "protos.json", PROTOCOLS);
+    let ok = write_file(
+        "monitor",
+        "ok.jsonl",
+        "{\"eventType\": \"partID\", \"role\": \"T\", \"payload\": {\"id\": 1}}\n{\"eventType\": \"pos\", \"role\": \"FL\"}\n",
+    );
+    let (code, json, _) = run(&["monitor", &protos, &ok]);
+    assert_eq!(code, 0);
+    assert_eq!(json["data"]["conforming"], 2);
+    assert_eq!(json["data"]["state"], "2");
+
+    let not_ok = write_file(
+        "monitor",
+        "not_ok.jsonl",
+        "{\"eventType\": \"partID\", \"role\": \"T\"}\n{\"eventType\": \"part\", \"role\": \"T\"}\n",
+    );
+    let (code, json, _) = run(&["monitor", &protos, &not_ok]);
+    assert_eq!(code, 1);
+    assert_eq!(json["type"], "OK");
+    assert_eq!(json["data"]["violation"]["index"], 1);
+    assert_eq!(json["data"]["violation"]["kind"], "NotEnabled");
+    assert_eq!(json["data"]["violation"]["state"], "1");
+    assert_eq!(json["data"]["violation"]["allowed"][0]["logType"][0], "pos");
+
+    let invalid = write_file("monitor", "invalid.jsonl", "{\"eventType\": \"partID\"}\n");
+    let (code, _, output) = run(&["monitor", &protos, &invalid]);
+    assert_eq!(code, 2);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 1"));
+}
+
+#[test]
+fn minimize_machine() {
+    let machine = write_file(
+        "minimize",
+        "machine.json",
+        r#"{
+            "initial": "0",
+            "transitions": [
+                { "source": "0", "target": "1", "label": { "tag": "Input", "eventType": "a" } },
+                { "source": "0", "target": "2", "label": { "tag": "Input", "eventType": "a" } },
+                { "source": "1", "target": "3", "label": { "tag": "Input", "eventType": "b" } },
+                { "source": "2", "target": "4", "label": { "tag": "Input", "eventType": "b" } }
+            ]
+        }"#,
+    );
+    let (code, json, _) = run(&["minimize-machine", &machine]);
+    assert_eq!(code, 0);
+    assert_eq!(json["type"], "OK");
+    assert_eq!(json["data"]["transitions"].as_array().unwrap().len(), 2);
+}
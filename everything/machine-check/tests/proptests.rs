@@ -1,6 +1,6 @@
 use machine_check::{
-    check_swarm, composition::{check_composed_projection, check_composed_swarm, compose_protocols, composition_types::{CompositionComponent, Granularity, InterfacingProtocols, InterfacingSwarms},
-    exact_well_formed_sub, overapproximated_well_formed_sub, project_combine, revised_projection}, types::{CheckResult, Command, DataResult, EventType, MachineLabel, Role, State, StateName, SwarmLabel, Transition}, well_formed_sub, EdgeId, Graph, MachineType, NodeId, Subscriptions, SwarmProtocolType
+    check_swarm, composition::{check_composed_projection, check_composed_swarm, check_composed_swarm_on_the_fly, compose_protocols, composition_types::{CompositionComponent, Granularity, InterfacingProtocols, InterfacingSwarms},
+    exact_well_formed_sub, overapproximated_well_formed_sub, project_combine, repair_subscription, revised_projection}, types::{CheckResult, Command, DataResult, EventType, MachineLabel, Role, State, StateName, SwarmLabel, Transition}, well_formed_sub, EdgeId, Graph, MachineType, NodeId, Subscriptions, SwarmProtocolType
 };
 use petgraph::{
     graph::EdgeReference,
@@ -292,6 +292,50 @@ prop_compose! {
 
 }
 
+// Make a protocol cyclic: redirect a transition into a terminal state to a state from which
+// the source of the transition can be reached. If the terminal state was the only one the protocol loops forever.
+fn loop_forever(mut proto: SwarmProtocolType) -> SwarmProtocolType {
+    let mut rng = rand::thread_rng();
+    let sources: BTreeSet<State> = proto.transitions.iter().map(|t| t.source.clone()).collect();
+    let into_terminal: Vec<usize> = (0..proto.transitions.len())
+        .filter(|i| !sources.contains(&proto.transitions[*i].target))
+        .collect();
+    let Some(index) = into_terminal.choose(&mut rng) else {
+        return proto;
+    };
+    // states from which the source of the transition can be reached
+    let mut ancestors = vec![proto.transitions[*index].source.clone()];
+    let mut i = 0;
+    while i < ancestors.len() {
+        for t in &proto.transitions {
+            if t.target == ancestors[i] && !ancestors.contains(&t.source) {
+                ancestors.push(t.source.clone());
+            }
+        }
+        i += 1;
+    }
+    proto.transitions[*index].target = ancestors.choose(&mut rng).unwrap().clone();
+    proto
+}
+
+// Aka general pattern, but with cycles and non-terminating protocols
+prop_compose! {
+    fn generate_interfacing_swarms_looping(max_roles: usize, max_events: usize, num_protos: usize)
+                (swarms in generate_interfacing_swarms_refinement_2(max_roles, max_events, num_protos))
+                -> InterfacingSwarms<Role> {
+        let mut rng = rand::thread_rng();
+        let b_dist = Bernoulli::new(0.5).unwrap();
+        InterfacingSwarms(swarms.0
+            .into_iter()
+            .enumerate()
+            .map(|(i, CompositionComponent { protocol, interface })| {
+                let protocol = if i == 0 || b_dist.sample(&mut rng) { loop_forever(protocol) } else { protocol };
+                CompositionComponent { protocol, interface }
+            })
+            .collect())
+    }
+}
+
 fn refinement_initial_proto() -> (Graph, NodeId) {
     let mut graph = Graph::new();
     let initial = graph.add_node(State::new(&fresh_i().to_string()));
@@ -598,7 +642,7 @@ proptest! {
         let errors = check_composed_swarm(to_interfacing_protocols(protos.clone()), subscription.clone());
         let ok = match errors {
             CheckResult::OK => true,
-            CheckResult::ERROR { errors: e } => {println!("{:?}", e); false}
+            CheckResult::ERROR { errors: e, .. } => {println!("{:?}", e); false}
         };
         assert!(ok);
     }
@@ -780,6 +824,96 @@ proptest! {
     }
 }
 
+// test that the exact and the fine and medium overapproximated subscriptions are well-formed
+// for compositions containing non-terminating protocols and that the overapproximations contain the exact subscription.
+proptest! {
+    #[test]
+    fn test_overapproximated_looping(protos in generate_interfacing_swarms_looping(5, 5, 3)) {
+        setup_logger();
+        let protos = to_interfacing_protocols(protos);
+        let subs = serde_json::to_string(&BTreeMap::<Role, BTreeSet::<EventType>>::new()).unwrap();
+        let exact = match exact_well_formed_sub(protos.clone(), subs.clone()) {
+            DataResult::OK{data: subscriptions} => subscriptions,
+            DataResult::ERROR{ errors, .. } => panic!("{:?}", errors),
+        };
+        if let CheckResult::ERROR { errors, .. } = check_composed_swarm(protos.clone(), serde_json::to_string(&exact).unwrap()) {
+            panic!("exact: {:?}", errors);
+        }
+        for granularity in [Granularity::Fine, Granularity::Medium] {
+            let subscription = match overapproximated_well_formed_sub(protos.clone(), subs.clone(), granularity.clone()) {
+                DataResult::OK{data: subscriptions} => subscriptions,
+                DataResult::ERROR{ errors, .. } => panic!("{:?}", errors),
+            };
+            if let CheckResult::ERROR { errors, .. } = check_composed_swarm(protos.clone(), serde_json::to_string(&subscription).unwrap()) {
+                panic!("{:?}: {:?}", granularity, errors);
+            }
+            for (role, event_types) in &exact {
+                assert!(event_types.is_subset(subscription.get(role).unwrap_or(&BTreeSet::new())));
+            }
+        }
+    }
+}
+
+// test that checking on the fly with partial-order reduction finds the same violations as checking the
+// expanded composition, for the empty subscription and for the exact subscription with one event type removed.
+proptest! {
+    #[test]
+    fn test_check_on_the_fly(protos in prop_oneof![generate_interfacing_swarms_refinement_2(5, 5, 3), generate_interfacing_swarms_looping(5, 5, 3)]) {
+        setup_logger();
+        let protos = to_interfacing_protocols(protos);
+        let empty = serde_json::to_string(&BTreeMap::<Role, BTreeSet::<EventType>>::new()).unwrap();
+        let mut exact = match exact_well_formed_sub(protos.clone(), empty.clone()) {
+            DataResult::OK{data: subscriptions} => subscriptions,
+            DataResult::ERROR{ errors, .. } => panic!("{:?}", errors),
+        };
+        if let Some(event_types) = exact.values_mut().find(|event_types| !event_types.is_empty()) {
+            let event_type = event_types.first().unwrap().clone();
+            event_types.remove(&event_type);
+        }
+        for subs in [empty, serde_json::to_string(&exact).unwrap()] {
+            let expected = check_composed_swarm(protos.clone(), subs.clone()).violation_keys();
+            assert_eq!(check_composed_swarm_on_the_fly(protos.clone(), subs.clone(), false).violation_keys(), expected);
+            let first = check_composed_swarm_on_the_fly(protos.clone(), subs, true).violation_keys();
+            assert_eq!(first.len(), cmp::min(expected.len(), 1));
+            assert!(first.is_subset(&expected));
+        }
+    }
+}
+
+// test that repairing the empty subscription and the exact subscription with one event type removed
+// gives a well-formed superset of the input and that every error of the input is fixed by some addition.
+proptest! {
+    #[test]
+    fn test_repair_subscription(protos in prop_oneof![generate_interfacing_swarms_refinement_2(5, 5, 3), generate_interfacing_swarms_looping(5, 5, 3)]) {
+        setup_logger();
+        let protos = to_interfacing_protocols(protos);
+        let empty = BTreeMap::<Role, BTreeSet::<EventType>>::new();
+        let mut exact = match exact_well_formed_sub(protos.clone(), serde_json::to_string(&empty).unwrap()) {
+            DataResult::OK{data: subscriptions} => subscriptions,
+            DataResult::ERROR{ errors, .. } => panic!("{:?}", errors),
+        };
+        if let Some(event_types) = exact.values_mut().find(|event_types| !event_types.is_empty()) {
+            let event_type = event_types.first().unwrap().clone();
+            event_types.remove(&event_type);
+        }
+        for subs in [empty, exact] {
+            let repair = match repair_subscription(protos.clone(), serde_json::to_string(&subs).unwrap()) {
+                DataResult::OK{data: repair} => repair,
+                DataResult::ERROR{ errors, .. } => panic!("{:?}", errors),
+            };
+            if let CheckResult::ERROR { errors, .. } = check_composed_swarm(protos.clone(), serde_json::to_string(&repair.subscription).unwrap()) {
+                panic!("repaired: {:?}", errors);
+            }
+            for (role, event_types) in &subs {
+                assert!(event_types.is_subset(repair.subscription.get(role).unwrap_or(&BTreeSet::new())));
+            }
+            for error in &repair.errors {
+                assert!(repair.added.iter().any(|addition| addition.fixes.contains(error)));
+            }
+        }
+    }
+}
+
 proptest! {
     #[test]
     #[ignore]
@@ -917,12 +1051,12 @@ proptest! {
             // should work like this projecting over the explicit composition initially and comparing that with combined machines?
             match check_composed_projection(to_interfacing_protocols(protos.clone()), sub_string.clone(), role.clone(), projection.clone().unwrap()) {
                 CheckResult::OK => (),
-                CheckResult::ERROR {errors: e} => {
+                CheckResult::ERROR { errors: e, .. } => {
                     match project_combine(to_interfacing_protocols(protos.clone()), sub_string.clone(), role.clone(), false) {
                         DataResult::OK{data: projection1} => {
                             println!("machine combined: {}", serde_json::to_string_pretty::<MachineType>(&projection1).unwrap());
                         },
-                        DataResult::ERROR{ errors: e } => println!("errors combined: {:?}", e),
+                        DataResult::ERROR{ errors: e, .. } => println!("errors combined: {:?}", e),
                     };
                     println!("machine: {}", serde_json::to_string_pretty(&projection.unwrap()).unwrap());
                     println!("composition: {}", serde_json::to_string_pretty(&composition).unwrap());
@@ -979,12 +1113,12 @@ proptest! {
                     println!("|combined states| - |expanded states| = |combined terminal| - |expanded terminal|: {}", (num_states(&combined.clone().unwrap()) - num_states(&projection.clone().unwrap())) == (num_terminal(&combined.clone().unwrap()) - num_terminal(&projection.clone().unwrap())));
                     println!("");
                 },//(),
-                CheckResult::ERROR {errors: e} => {
+                CheckResult::ERROR { errors: e, .. } => {
                     match project_combine(to_interfacing_protocols(protos.clone()), sub_string.clone(), role.clone(), false) {
                         DataResult::OK{data: projection1} => {
                             println!("machine combined: {}", serde_json::to_string_pretty::<MachineType>(&projection1).unwrap());
                         },
-                        DataResult::ERROR{ errors: e } => println!("errors combined: {:?}", e),
+                        DataResult::ERROR{ errors: e, .. } => println!("errors combined: {:?}", e),
                     };
                     println!("machine: {}", serde_json::to_string_pretty(&projection.unwrap()).unwrap());
                     /* println!("composition: {}", serde_json::to_string_pretty(&composition).unwrap());
@@ -99,7 +99,7 @@ fn full_simple_run_bench_sub_sizes_general() {
         };
         match check_swarm(input.proto.clone(), serde_json::to_string(&subscriptions_wf_kmt.clone().unwrap()).unwrap()) {
             CheckResult::OK => (),
-            CheckResult::ERROR { errors } => { println!("id: {}, cause: {},\n subscriptions: {}",
+            CheckResult::ERROR { errors, .. } => { println!("id: {}, cause: {},\n subscriptions: {}",
             input.id.clone().unwrap_or(String::from("")), errors.join(", "), serde_json::to_string_pretty(&subscriptions_wf_kmt.clone()).unwrap());
             panic!("Not ok compositional") }
         }
@@ -112,7 +112,7 @@ fn full_simple_run_bench_sub_sizes_general() {
         };
         match check_composed_swarm(InterfacingProtocols(vec![input.proto.clone()]), serde_json::to_string(&subscriptions_compositional_exact.clone().unwrap()).unwrap()) {
             CheckResult::OK => (),
-            CheckResult::ERROR { errors } => { println!("id: {}, cause: {},\n subscriptions: {}",
+            CheckResult::ERROR { errors, .. } => { println!("id: {}, cause: {},\n subscriptions: {}",
             input.id.clone().unwrap_or(String::from("")), errors.join(", "), serde_json::to_string_pretty(&subscriptions_compositional_exact.clone()).unwrap());
             panic!("Not ok compositional") }
         }
@@ -125,7 +125,7 @@ fn full_simple_run_bench_sub_sizes_general() {
         };
         match check_composed_swarm(InterfacingProtocols(vec![input.proto.clone()]), serde_json::to_string(&subscriptions_compositional_approx.clone().unwrap()).unwrap()) {
             CheckResult::OK => (),
-            CheckResult::ERROR { errors } => { println!("id: {}, cause: {},\n subscriptions: {}",
+            CheckResult::ERROR { errors, .. } => { println!("id: {}, cause: {},\n subscriptions: {}",
             input.id.clone().unwrap_or(String::from("")), errors.join(", "), serde_json::to_string_pretty(&subscriptions_compositional_approx.clone()).unwrap());
             panic!("Not ok compositional") }
         }
@@ -153,7 +153,7 @@ fn short_simple_run_bench_sub_sizes_general() {
         };
         match check_swarm(input.proto.clone(), serde_json::to_string(&subscriptions_wf_kmt.clone().unwrap()).unwrap()) {
             CheckResult::OK => (),
-            CheckResult::ERROR { errors } => { println!("id: {}, cause: {},\n subscriptions: {}",
+            CheckResult::ERROR { errors, .. } => { println!("id: {}, cause: {},\n subscriptions: {}",
             input.id.clone().unwrap_or(String::from("")), errors.join(", "), serde_json::to_string_pretty(&subscriptions_wf_kmt.clone()).unwrap());
             panic!("Not ok compositional") }
         }
@@ -166,7 +166,7 @@ fn short_simple_run_bench_sub_sizes_general() {
         };
         match check_composed_swarm(InterfacingProtocols(vec![input.proto.clone()]), serde_json::to_string(&subscriptions_compositional_exact.clone().unwrap()).unwrap()) {
             CheckResult::OK => (),
-            CheckResult::ERROR { errors } => { println!("id: {}, cause: {},\n subscriptions: {}",
+            CheckResult::ERROR { errors, .. } => { println!("id: {}, cause: {},\n subscriptions: {}",
             input.id.clone().unwrap_or(String::from("")), errors.join(", "), serde_json::to_string_pretty(&subscriptions_compositional_exact.clone()).unwrap());
             panic!("Not ok compositional") }
         }
@@ -179,7 +179,7 @@ fn short_simple_run_bench_sub_sizes_general() {
         };
         match check_composed_swarm(InterfacingProtocols(vec![input.proto.clone()]), serde_json::to_string(&subscriptions_compositional_approx.clone().unwrap()).unwrap()) {
             CheckResult::OK => (),
-            CheckResult::ERROR { errors } => { println!("id: {}, cause: {},\n subscriptions: {}",
+            CheckResult::ERROR { errors, .. } => { println!("id: {}, cause: {},\n subscriptions: {}",
             input.id.clone().unwrap_or(String::from("")), errors.join(", "), serde_json::to_string_pretty(&subscriptions_compositional_approx.clone()).unwrap());
             panic!("Not ok compositional") }
         }